@@ -0,0 +1,411 @@
+use crate::*;
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use std::{fs::write, path::PathBuf};
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// List entries with the scope, file and line they come from
+    List {
+        /// Only list this scope's file [default: every scope, includes followed]
+        #[arg(long)]
+        scope: Option<GitConfig>,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Show a key's value at every scope and included file, and which one wins
+    Explain {
+        key: String,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Compare the keys of two scopes, files, repositories or presets
+    ///
+    /// Each source is a scope (system, global, local, worktree, file:<path>),
+    /// repo:<path> for the local config of another repository, or preset:<name>.
+    Diff {
+        left: ConfigSource,
+        right: ConfigSource,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Check every scope for common misconfigurations, optionally fixing them
+    Lint {
+        /// Offer the automatic fix of every finding that has one
+        #[arg(long)]
+        fix: bool,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Print the value of a key
+    Get {
+        key: String,
+        /// Print every value of a multivar key
+        #[arg(long)]
+        all: bool,
+        #[arg(long, default_value = "local")]
+        scope: GitConfig,
+    },
+    /// Print every key and value whose key matches a regular expression
+    GetRegexp {
+        pattern: String,
+        #[arg(long, default_value = "local")]
+        scope: GitConfig,
+    },
+    /// Set a key, asking before an existing value is replaced
+    Set {
+        key: String,
+        value: String,
+        #[arg(long, default_value = "local")]
+        scope: GitConfig,
+    },
+    /// Add a value to a multivar key, keeping the existing ones
+    Add {
+        key: String,
+        value: String,
+        #[arg(long, default_value = "local")]
+        scope: GitConfig,
+    },
+    /// Remove a key
+    Unset {
+        key: String,
+        /// Remove every value of a multivar key
+        #[arg(long)]
+        all: bool,
+        #[arg(long, default_value = "local")]
+        scope: GitConfig,
+    },
+    /// Rename a section, such as remote.origin to remote.upstream
+    RenameSection {
+        old: String,
+        new: String,
+        #[arg(long, default_value = "local")]
+        scope: GitConfig,
+    },
+    /// List the shipped and user-defined presets
+    Presets,
+    /// Show what a preset would change, then apply it
+    Apply {
+        preset: String,
+        /// Apply every entry to this scope instead of the one in the preset
+        #[arg(long)]
+        scope: Option<GitConfig>,
+    },
+    /// Write a scope's config as a portable TOML or JSON document
+    Export {
+        #[arg(long, default_value = "global")]
+        scope: GitConfig,
+        /// toml or json [default: from the output extension, else toml]
+        #[arg(long)]
+        format: Option<ConfigDocumentFormat>,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Replay an exported document into a scope, reporting conflicting values first
+    Import {
+        file: PathBuf,
+        /// Import into this scope instead of the one the document was exported from
+        #[arg(long)]
+        scope: Option<GitConfig>,
+    },
+    /// List the backups taken before gitsy changed a config file
+    History,
+    /// Restore the config file changed most recently to its backup
+    Undo,
+    /// Restore a config file to one of its backups
+    Restore { id: String },
+    /// Remove a section with all of its keys
+    RemoveSection {
+        name: String,
+        #[arg(long, default_value = "local")]
+        scope: GitConfig,
+    },
+}
+
+impl ConfigCommand {
+    pub fn run(self) -> Result<()> {
+        match self {
+            Self::List { scope, format } => {
+                let entries = match scope {
+                    Some(scope) => scope.list()?,
+                    None => GitConfig::list_all()?,
+                };
+                print_entries(&entries, format)
+            }
+            Self::Explain { key, format } => {
+                let explanation = ConfigExplanation::resolve(&key)?;
+                match format {
+                    OutputFormat::Table => print!("{}", explanation),
+                    OutputFormat::Json => {
+                        println!("{}", serde_json::to_string_pretty(&explanation)?)
+                    }
+                }
+                Ok(())
+            }
+            Self::Diff {
+                left,
+                right,
+                format,
+            } => {
+                let diff = ConfigDiff::between(&left, &right)?;
+                match format {
+                    OutputFormat::Table => print!("{}", diff),
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+                }
+                Ok(())
+            }
+            Self::Lint { fix, format } => lint(fix, format),
+            Self::Get {
+                key,
+                all: true,
+                scope,
+            } => match scope.get_all(&key)?.as_slice() {
+                [] => Err(anyhow!("No {} value for '{}'", scope, key)),
+                values => {
+                    values.iter().for_each(|value| println!("{}", value));
+                    Ok(())
+                }
+            },
+            Self::Get { key, scope, .. } => match scope.get_value(&key)? {
+                Some(value) => {
+                    println!("{}", value);
+                    Ok(())
+                }
+                None => Err(anyhow!("No {} value for '{}'", scope, key)),
+            },
+            Self::GetRegexp { pattern, scope } => {
+                for (key, value) in scope.get_regexp(&pattern)? {
+                    println!("{} {}", key, value);
+                }
+                Ok(())
+            }
+            Self::Set { key, value, scope } => scope.set_value(&key, &value),
+            Self::Add { key, value, scope } => scope.add(&key, &value),
+            Self::Unset { key, all, scope } if all => scope.unset_all(&key),
+            Self::Unset { key, scope, .. } => scope.unset(&key),
+            Self::RenameSection { old, new, scope } => scope.rename_section(&old, &new),
+            Self::RemoveSection { name, scope } => scope.remove_section(&name),
+            Self::Export {
+                scope,
+                format,
+                output,
+            } => {
+                let format = format
+                    .or_else(|| output.as_deref().map(ConfigDocumentFormat::from_path))
+                    .unwrap_or_default();
+                let rendered = ConfigDocument::export(&scope)?.render(format)?;
+                match output {
+                    Some(path) if is_dry_run() => println!(
+                        "[dry-run] Would export the {} git config to {}",
+                        scope,
+                        path.display()
+                    ),
+                    Some(path) => {
+                        write(&path, rendered)?;
+                        println!("Exported the {} git config to {}", scope, path.display());
+                    }
+                    None => print!("{}", rendered),
+                }
+                Ok(())
+            }
+            Self::Import { file, scope } => {
+                let document = ConfigDocument::load(&file)?;
+                let scope = scope.unwrap_or_else(|| document.scope.clone());
+                import_document(&document, &scope)
+            }
+            Self::History => {
+                let rows: Vec<Vec<String>> = ConfigSnapshot::list()?
+                    .into_iter()
+                    .map(|snapshot| {
+                        let status = match (snapshot.undone, snapshot.before_restore) {
+                            (true, _) => "undone",
+                            (_, true) => "before restore",
+                            _ => "",
+                        };
+                        vec![
+                            snapshot.id,
+                            snapshot.scope,
+                            tilde_path(&snapshot.path),
+                            snapshot.changes.join(", "),
+                            status.to_string(),
+                        ]
+                    })
+                    .collect();
+                print_table(&["ID", "SCOPE", "FILE", "CHANGES", "STATUS"], &rows);
+                Ok(())
+            }
+            Self::Undo => {
+                let mut snapshot = ConfigSnapshot::last_undoable()?
+                    .ok_or_else(|| anyhow!("There is no config change to undo"))?;
+                if restore_snapshot(&snapshot)? {
+                    snapshot.mark_undone()?;
+                }
+                Ok(())
+            }
+            Self::Restore { id } => restore_snapshot(&ConfigSnapshot::load(&id)?).map(|_| ()),
+            Self::Presets => {
+                let rows: Vec<Vec<String>> = ConfigPreset::list()?
+                    .into_iter()
+                    .map(|preset| {
+                        let source = match &preset.source {
+                            Some(PresetSource::File(path)) => tilde_path(path),
+                            _ => "shipped".to_string(),
+                        };
+                        vec![preset.name, preset.description, source]
+                    })
+                    .collect();
+                print_table(&["NAME", "DESCRIPTION", "SOURCE"], &rows);
+                Ok(())
+            }
+            Self::Apply { preset, scope } => {
+                let plan = ConfigPreset::load(&preset)?.plan(scope.as_ref())?;
+                print!("{}", plan);
+                if plan.is_empty()
+                    || is_dry_run()
+                    || !permission_granted(format!(
+                        "Apply {} changes from the preset '{}'?",
+                        plan.actions.len(),
+                        preset
+                    ))
+                {
+                    return Ok(());
+                }
+                plan.apply_confirmed()
+            }
+        }
+    }
+}
+
+fn lint(fix: bool, format: OutputFormat) -> Result<()> {
+    let mut findings = LintFinding::check_all()?;
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&findings)?),
+        OutputFormat::Table => {
+            findings.iter().for_each(|finding| println!("{}", finding));
+            let fixable = findings
+                .iter()
+                .filter(|finding| finding.fix.is_some())
+                .count();
+            println!(
+                "{} findings, {} with an automatic fix{}",
+                findings.len(),
+                fixable,
+                if fixable > 0 && !fix {
+                    " (run with --fix)"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+
+    if fix {
+        for finding in &findings {
+            let Some(plan) = &finding.fix else {
+                continue;
+            };
+            if is_dry_run() {
+                print!("{}", plan);
+            } else if permission_granted(format!("Fix: {}?", finding.message)) {
+                plan.apply_confirmed()?;
+            }
+        }
+        if !is_dry_run() {
+            findings = LintFinding::check_all()?;
+        }
+    }
+
+    match findings
+        .iter()
+        .filter(|finding| finding.severity == LintSeverity::Error)
+        .count()
+    {
+        0 => Ok(()),
+        count => Err(anyhow!("Found {} config errors", count)),
+    }
+}
+
+fn import_document(document: &ConfigDocument, scope: &GitConfig) -> Result<()> {
+    let plan = document.plan_import(scope)?;
+    print!("{}", plan);
+
+    let conflicts = import_conflicts(&plan);
+    if !conflicts.is_empty() {
+        println!(
+            "{} conflicting keys already have a different value in the {} config:",
+            conflicts.len(),
+            scope
+        );
+        for (key, old, new) in &conflicts {
+            println!("  {}: '{}' (current) vs '{}' (imported)", key, old, new);
+        }
+    }
+
+    if plan.is_empty() || is_dry_run() {
+        return Ok(());
+    }
+    if !permission_granted(format!(
+        "Import {} changes into the {} config?",
+        plan.actions.len(),
+        scope
+    )) {
+        return Ok(());
+    }
+    plan.apply_confirmed()
+}
+
+/// Shows the diff a restore would apply and restores once confirmed. Returns whether it was restored.
+fn restore_snapshot(snapshot: &ConfigSnapshot) -> Result<bool> {
+    let plan = snapshot.plan_restore()?;
+    print!("{}", plan);
+    if plan.is_empty() || is_dry_run() {
+        return Ok(false);
+    }
+
+    if !permission_granted(format!(
+        "Restore {} to the backup {} ({})?",
+        tilde_path(&snapshot.path),
+        snapshot.id,
+        snapshot.changes.join(", ")
+    )) {
+        return Ok(false);
+    }
+    snapshot.restore()?;
+    println!(
+        "Restored {} from {}",
+        tilde_path(&snapshot.path),
+        snapshot.id
+    );
+    Ok(true)
+}
+
+fn print_entries(entries: &[GitConfigEntry], format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(entries)?),
+        OutputFormat::Table => {
+            let rows: Vec<Vec<String>> = entries
+                .iter()
+                .map(|entry| {
+                    vec![
+                        entry.scope.to_str().to_string(),
+                        entry.key.clone(),
+                        entry.value_str().to_string(),
+                        format_origin(entry),
+                    ]
+                })
+                .collect();
+            print_table(&["SCOPE", "KEY", "VALUE", "ORIGIN"], &rows);
+        }
+    }
+    Ok(())
+}
+
+/// `path:line`, or just the path when the line is unknown.
+pub(crate) fn format_origin(entry: &GitConfigEntry) -> String {
+    match (&entry.origin, entry.line) {
+        (Some(origin), Some(line)) => format!("{}:{}", origin.display(), line),
+        (Some(origin), None) => origin.display().to_string(),
+        (None, _) => "-".to_string(),
+    }
+}
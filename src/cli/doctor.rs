@@ -0,0 +1,50 @@
+use crate::*;
+use anyhow::{anyhow, Result};
+use clap::Args;
+
+#[derive(Debug, Args)]
+pub struct DoctorArgs {
+    /// Check that this host is in known_hosts [default: the hosts of the repository's SSH remotes]
+    #[arg(long = "host")]
+    pub hosts: Vec<String>,
+    /// Offer the safe fix of every check that has one
+    #[arg(long)]
+    pub fix: bool,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+impl DoctorArgs {
+    pub fn run(self) -> Result<()> {
+        let mut checks = HealthCheck::run_all(&self.hosts);
+        match self.format {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&checks)?),
+            OutputFormat::Table => checks.iter().for_each(|check| print!("{}", check)),
+        }
+
+        if self.fix {
+            for check in &checks {
+                let Some(plan) = &check.fix else {
+                    continue;
+                };
+                if is_dry_run() {
+                    print!("{}", plan);
+                } else if permission_granted(format!("Fix: {}?", check.detail)) {
+                    plan.apply()?;
+                }
+            }
+            if !is_dry_run() {
+                checks = HealthCheck::run_all(&self.hosts);
+            }
+        }
+
+        match checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .count()
+        {
+            0 => Ok(()),
+            count => Err(anyhow!("{} checks failed", count)),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::*;
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+
+/// Identity fields shared by `init`, `key`, `ssh` and `profile`; anything left out is derived by `GitInit`.
+#[derive(Debug, Clone, Default, Args)]
+pub struct IdentityArgs {
+    /// Git user name, also used as the SSH user and key file name
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Git user email
+    #[arg(long)]
+    pub email: Option<String>,
+
+    /// Git host, e.g. github.com
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// Comment stored in the public key
+    #[arg(long)]
+    pub label: Option<String>,
+
+    /// Register the host as <HOST>-<ALIAS> so several accounts can share one provider
+    #[arg(long)]
+    pub alias: Option<String>,
+
+    /// Private key path, absolute or relative to the SSH directory
+    #[arg(long)]
+    pub key: Option<PathBuf>,
+
+    /// SSH directory [default: ~/.ssh]
+    #[arg(long)]
+    pub ssh_dir: Option<PathBuf>,
+
+    /// SSH config file, absolute or relative to the SSH directory
+    #[arg(long)]
+    pub ssh_config: Option<PathBuf>,
+}
+
+impl IdentityArgs {
+    pub fn into_profile(self) -> Profile {
+        Profile {
+            name: self.name.unwrap_or_default(),
+            email: self.email.unwrap_or_default(),
+            host: self.host.unwrap_or_default(),
+            label: self.label.unwrap_or_default(),
+            alias: self.alias,
+            key: self.key,
+            ssh_dir: self.ssh_dir,
+            ssh_config: self.ssh_config,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Args)]
+pub struct InitArgs {
+    /// Start from a saved profile; identity flags override its fields
+    #[arg(short, long)]
+    pub profile: Option<String>,
+
+    #[command(flatten)]
+    pub identity: IdentityArgs,
+
+    /// Replace an existing key pair
+    #[arg(long)]
+    pub regenerate: bool,
+
+    /// Generate keys with gitsy itself or with ssh-keygen
+    /// [default: ssh-keygen when it is installed, else native]
+    #[arg(long, value_name = "BACKEND")]
+    pub keygen: Option<KeygenBackend>,
+
+    /// Key algorithm and size: ed25519, ecdsa[-256|-384] or rsa[-3072|-4096] [default: ed25519]
+    #[arg(long, value_name = "TYPE")]
+    pub key_type: Option<KeyType>,
+
+    /// bcrypt-pbkdf rounds protecting the private key [default: 100]
+    #[arg(long, value_name = "ROUNDS")]
+    pub kdf_rounds: Option<u32>,
+
+    /// Where the passphrase comes from: prompt, none, env:<VAR> or file:<PATH> [default: prompt]
+    #[arg(long, value_name = "SOURCE")]
+    pub passphrase: Option<PassphraseSource>,
+
+    /// Public key comment built from {name}, {email}, {host}, {alias}, {label}, {user},
+    /// {device}, {distro} and {date} [default: the label]
+    #[arg(long, value_name = "TEMPLATE")]
+    pub comment: Option<String>,
+}
+
+impl InitArgs {
+    pub fn into_git_init(self) -> Result<GitInit> {
+        let mut profile = match &self.profile {
+            Some(id) => ProfileStore::load()?.get(id)?.clone(),
+            None => Profile::default(),
+        };
+        profile.merge(self.identity.into_profile());
+
+        let mut git_init = profile.to_git_init();
+        if let Some(backend) = self.keygen {
+            git_init = git_init.with_key_backend(backend);
+        }
+        if let Some(key_type) = self.key_type {
+            git_init = git_init.with_key_type(key_type.algorithm, Some(key_type.bits));
+        }
+        if let Some(rounds) = self.kdf_rounds {
+            git_init = git_init.with_kdf_rounds(rounds);
+        }
+        if let Some(source) = self.passphrase {
+            git_init = git_init.with_passphrase(source);
+        }
+        if let Some(template) = self.comment {
+            git_init = git_init.with_comment(template);
+        }
+        if self.regenerate {
+            Ok(git_init.regenerate())
+        } else {
+            Ok(git_init)
+        }
+    }
+}
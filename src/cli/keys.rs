@@ -0,0 +1,123 @@
+use crate::*;
+use anyhow::Result;
+use clap::Subcommand;
+use std::path::PathBuf;
+
+#[derive(Debug, Subcommand)]
+pub enum KeysCommand {
+    /// List the keys in the SSH directory with what uses them, flagging orphans and half pairs
+    List {
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Generate a new key pair for an identity, keeping the old one until the rotation is finalized
+    Rotate(Box<InitArgs>),
+    /// Delete the old key pair once the new key is known to work
+    Finalize {
+        /// Old or new private key of the rotation [default: the only rotation in progress]
+        key: Option<PathBuf>,
+    },
+    /// Put the old key pair back, point the host at it again and delete the new one
+    Abort {
+        /// Old or new private key of the rotation [default: the only rotation in progress]
+        key: Option<PathBuf>,
+    },
+    /// List the rotations in progress
+    Rotations,
+}
+
+impl KeysCommand {
+    pub fn run(self) -> Result<()> {
+        let mut rotations = KeyRotations::load()?;
+
+        match self {
+            Self::List { format } => {
+                let keys = SshKeyEntry::scan_all()?;
+                match format {
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&keys)?),
+                    OutputFormat::Table => print_key_table(&keys),
+                }
+                Ok(())
+            }
+            Self::Rotate(args) => {
+                let mut git_init = args.into_git_init()?;
+                let (plan, rotation) = git_init.plan_rotation(&rotations)?;
+                print!("{}", plan);
+                if is_dry_run()
+                    || !permission_granted(format!(
+                        "Rotate {} to {}?",
+                        tilde_path(&rotation.private_key),
+                        tilde_path(&rotation.new_private_key)
+                    ))
+                {
+                    return Ok(());
+                }
+
+                //> Record the rotation first, so that a half-applied one can still be aborted
+                rotations.add(rotation.clone())?;
+                rotations.save()?;
+                if let Err(error) = plan.apply() {
+                    if !rotation.archived_private_key.exists() {
+                        rotations.remove(&rotation.private_key);
+                        rotations.save()?;
+                    }
+                    return Err(error);
+                }
+
+                println!(
+                    "Add {} to {}, check it with `ssh -T git@{}`, then run `gitsy keys finalize` \
+                     (or `gitsy keys abort` to go back to the old key).",
+                    tilde_path(&rotation.new_public_key),
+                    git_init.host,
+                    rotation.host
+                );
+                Ok(())
+            }
+            Self::Finalize { key } => {
+                let rotation = rotations.find(key.map(expand_key).as_deref())?.clone();
+                let plan = rotation.plan_finalize();
+                print!("{}", plan);
+                if is_dry_run()
+                    || !permission_granted(format!(
+                        "Delete the old key {} for good?",
+                        tilde_path(&rotation.archived_private_key)
+                    ))
+                {
+                    return Ok(());
+                }
+                plan.apply()?;
+                rotations.remove(&rotation.private_key);
+                rotations.save()
+            }
+            Self::Abort { key } => {
+                let rotation = rotations.find(key.map(expand_key).as_deref())?.clone();
+                let plan = rotation.plan_abort()?;
+                print!("{}", plan);
+                if is_dry_run()
+                    || !permission_granted(format!(
+                        "Restore the old key {} and delete the new one?",
+                        tilde_path(&rotation.private_key)
+                    ))
+                {
+                    return Ok(());
+                }
+                plan.apply()?;
+                rotations.remove(&rotation.private_key);
+                rotations.save()
+            }
+            Self::Rotations => {
+                rotations
+                    .rotations
+                    .iter()
+                    .for_each(|rotation| println!("{}", rotation));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A key given on the command line, with `~` expanded and made absolute.
+fn expand_key(key: PathBuf) -> PathBuf {
+    let key = expand_tilde(&key.to_string_lossy());
+    std::path::absolute(&key).unwrap_or(key)
+}
@@ -0,0 +1,161 @@
+mod config;
+mod doctor;
+mod identity;
+mod keys;
+mod profile;
+mod remote;
+mod ssh;
+mod up;
+mod whoami;
+
+use crate::*;
+use anyhow::Result;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{env::set_current_dir, path::PathBuf, process::ExitCode};
+
+pub use config::*;
+pub use doctor::*;
+pub use identity::*;
+pub use keys::*;
+pub use profile::*;
+pub use remote::*;
+pub use ssh::*;
+pub use up::*;
+pub use whoami::*;
+
+/// Git identity, SSH key and config management.
+#[derive(Debug, Parser)]
+#[command(name = "gitsy", version, about)]
+pub struct Cli {
+    /// Run as if gitsy was started in <REPO> instead of the current directory
+    #[arg(short = 'C', value_name = "REPO", global = true)]
+    pub repo: Option<PathBuf>,
+
+    /// Answer "yes" to every confirmation prompt
+    #[arg(short, long, global = true, conflicts_with_all = ["no", "defaults"])]
+    pub yes: bool,
+
+    /// Answer "no" to every confirmation prompt
+    #[arg(long, global = true, conflicts_with = "defaults")]
+    pub no: bool,
+
+    /// Take the default answer of every prompt [implied when stdin is not a terminal,
+    /// see also GITSY_ASSUME=yes|no|default|interactive]
+    #[arg(long, global = true)]
+    pub defaults: bool,
+
+    /// Show what would change without touching any file or config
+    #[arg(short = 'n', long, global = true)]
+    pub dry_run: bool,
+
+    /// Read and write git config by running git or by editing the files directly
+    /// [default: git when it is installed; see also GITSY_CONFIG_BACKEND=git|native]
+    #[arg(long, value_name = "BACKEND", global = true)]
+    pub backend: Option<GitConfigBackend>,
+
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Generate the key pair, register it with SSH and set the local git identity
+    Init(InitArgs),
+    /// Inspect or change git config values
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Generate the SSH key pair for an identity
+    Key(InitArgs),
+    /// List and rotate SSH keys
+    #[command(subcommand)]
+    Keys(KeysCommand),
+    /// Parse and rewrite remote URLs
+    #[command(subcommand)]
+    Remote(RemoteCommand),
+    /// Edit and check the SSH config
+    #[command(subcommand)]
+    Ssh(SshCommand),
+    /// Manage saved identities
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+    /// Pull, re-apply gitignore, add and commit everything, then push
+    Up(UpArgs),
+    /// Show the system user and the git identity in effect
+    Whoami,
+    /// Check git, ssh, key permissions and the repository identity, offering safe fixes
+    Doctor(DoctorArgs),
+}
+
+impl Cli {
+    pub fn run(self) -> Result<()> {
+        if let Some(repo) = &self.repo {
+            set_current_dir(repo)?;
+        }
+        match (self.yes, self.no, self.defaults) {
+            (true, _, _) => set_answer_mode(AnswerMode::Yes),
+            (_, true, _) => set_answer_mode(AnswerMode::No),
+            (_, _, true) => set_answer_mode(AnswerMode::Default),
+            _ => {}
+        }
+        set_dry_run(self.dry_run);
+        if let Some(backend) = self.backend {
+            set_config_backend(backend);
+        }
+
+        match self.command {
+            Commands::Init(args) => args.into_git_init()?.execute(),
+            Commands::Config(command) => command.run(),
+            Commands::Key(args) => args.into_git_init()?.generate_key(),
+            Commands::Keys(command) => command.run(),
+            Commands::Remote(command) => command.run(),
+            Commands::Ssh(command) => command.run(),
+            Commands::Profile(command) => command.run(),
+            Commands::Up(args) => args.run(),
+            Commands::Whoami => print_whoami(),
+            Commands::Doctor(args) => args.run(),
+        }
+    }
+}
+
+/// How listing commands render their results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+pub fn run_cli() -> ExitCode {
+    match Cli::parse().run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {:#}", error);
+            match error.downcast_ref::<GitUpError>() {
+                Some(git_up_error) => ExitCode::from(git_up_error.step.exit_code()),
+                None => ExitCode::FAILURE,
+            }
+        }
+    }
+}
+
+#[test]
+fn test_cli_definition() {
+    use clap::CommandFactory;
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn test_global_flags_after_subcommand() {
+    let cli = Cli::parse_from([
+        "gitsy",
+        "config",
+        "list",
+        "-C",
+        "/tmp",
+        "--yes",
+        "--dry-run",
+    ]);
+    assert_eq!(cli.repo, Some(PathBuf::from("/tmp")));
+    assert!(cli.yes);
+    assert!(cli.dry_run);
+}
@@ -0,0 +1,108 @@
+use crate::*;
+use anyhow::Result;
+use clap::Subcommand;
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileCommand {
+    /// Save a new identity
+    Add {
+        id: String,
+        #[command(flatten)]
+        identity: IdentityArgs,
+    },
+    /// List saved profiles
+    List,
+    /// Print a profile
+    Show { id: String },
+    /// Change the given fields of a profile
+    Edit {
+        id: String,
+        #[command(flatten)]
+        identity: IdentityArgs,
+    },
+    /// Delete a profile and its directory bindings
+    Remove { id: String },
+    /// Use a profile for every repository under a directory, via includeIf in the global config
+    Bind { id: String, directory: String },
+    /// Stop using a profile for a directory
+    Unbind { directory: String },
+    /// List which directories map to which profile
+    Dirs,
+}
+
+impl ProfileCommand {
+    pub fn run(self) -> Result<()> {
+        let mut store = ProfileStore::load()?;
+
+        match self {
+            Self::Add { id, identity } => {
+                let profile = identity.into_profile();
+                let sharing_host = store.profiles.iter().find(|(_, other)| {
+                    !profile.host.is_empty()
+                        && other.host == profile.host
+                        && (other.alias.is_none() || profile.alias.is_none())
+                });
+                if let Some((other, _)) = sharing_host {
+                    eprintln!(
+                        "Warning: '{}' also uses {}; give both profiles an --alias so they get separate Host blocks",
+                        other, profile.host
+                    );
+                }
+                store.add(&id, profile)?;
+                save_store(&store, &id, "Added")
+            }
+            Self::List => {
+                for (id, profile) in &store.profiles {
+                    println!(
+                        "{:<16} {:<24} {:<32} {}",
+                        id, profile.name, profile.email, profile.host
+                    );
+                }
+                Ok(())
+            }
+            Self::Show { id } => {
+                print!("{}", store.get(&id)?);
+                Ok(())
+            }
+            Self::Edit { id, identity } => {
+                store.get_mut(&id)?.merge(identity.into_profile());
+                save_store(&store, &id, "Updated")?;
+                ProfileBinding::plan_sync(&id, store.get(&id)?)?.run()
+            }
+            Self::Remove { id } => {
+                store.get(&id)?;
+                if !permission_granted(format!("Remove the profile '{}'?", id)) {
+                    return Ok(());
+                }
+                ProfileBinding::plan_remove_all(&id)?.run()?;
+                store.remove(&id)?;
+                save_store(&store, &id, "Removed")
+            }
+            Self::Bind { id, directory } => {
+                ProfileBinding::plan_bind(&id, store.get(&id)?, &directory)?.run()
+            }
+            Self::Unbind { directory } => ProfileBinding::plan_unbind(&directory)?.run(),
+            Self::Dirs => {
+                ProfileBinding::list()?
+                    .iter()
+                    .for_each(|binding| println!("{}", binding));
+                Ok(())
+            }
+        }
+    }
+}
+
+fn save_store(store: &ProfileStore, id: &str, action: &str) -> Result<()> {
+    if is_dry_run() {
+        println!(
+            "[dry-run] Would save profile '{}' to {}",
+            id,
+            store.path.display()
+        );
+        return Ok(());
+    }
+
+    store.save()?;
+    println!("{} profile '{}' in {}", action, id, store.path.display());
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use crate::*;
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+
+#[derive(Debug, Subcommand)]
+pub enum RemoteCommand {
+    /// Show the parts of every remote URL
+    Show,
+    /// Switch a remote to SSH
+    ToSsh {
+        #[arg(default_value = "origin")]
+        remote: String,
+    },
+    /// Switch a remote to HTTPS, resolving SSH host aliases to the real host
+    ToHttps {
+        #[arg(default_value = "origin")]
+        remote: String,
+    },
+    /// Point a remote at a profile's SSH host alias
+    Use {
+        profile: String,
+        #[arg(default_value = "origin")]
+        remote: String,
+    },
+}
+
+impl RemoteCommand {
+    pub fn run(self) -> Result<()> {
+        match self {
+            Self::Show => {
+                for (remote, url) in get_remote_urls()? {
+                    match RemoteUrl::parse(&url) {
+                        Ok(parsed) => println!(
+                            "{:<12} {:<6} {:<24} {:<24} {}",
+                            remote,
+                            parsed.scheme.to_str(),
+                            parsed.host,
+                            parsed.owner(),
+                            parsed.repo()
+                        ),
+                        Err(error) => println!("{:<12} {}", remote, error),
+                    }
+                }
+                Ok(())
+            }
+            Self::ToSsh { remote } => {
+                let url = get_remote_url(&remote)?;
+                plan_set_remote(&remote, &url, url.to_ssh()?)
+            }
+            Self::ToHttps { remote } => {
+                let url = get_remote_url(&remote)?;
+                let host = resolve_ssh_host(&url.host)?;
+                plan_set_remote(&remote, &url, url.to_https()?.with_host(host))
+            }
+            Self::Use { profile, remote } => {
+                let git_init = ProfileStore::load()?.get(&profile)?.to_git_init();
+                if git_init.alias.is_none() {
+                    return Err(anyhow!("Profile '{}' has no --alias", profile));
+                }
+
+                let url = get_remote_url(&remote)?;
+                if !resolve_ssh_host(&url.host)?.eq_ignore_ascii_case(&git_init.host) {
+                    return Err(anyhow!(
+                        "Remote '{}' is on {}, not on the profile's host {}",
+                        remote,
+                        url.host,
+                        git_init.host
+                    ));
+                }
+                plan_set_remote(&remote, &url, url.to_ssh()?.with_host(git_init.ssh_host()))
+            }
+        }
+    }
+}
+
+fn get_remote_url(remote: &str) -> Result<RemoteUrl> {
+    let url = get_remote_urls()?
+        .into_iter()
+        .find(|(name, _)| name == remote)
+        .map(|(_, url)| url)
+        .ok_or_else(|| anyhow!("No remote named '{}'", remote))?;
+    RemoteUrl::parse(&url)
+}
+
+/// The `HostName` behind an SSH host alias, from the SSH config or a profile, or `host` itself.
+fn resolve_ssh_host(host: &str) -> Result<String> {
+    let ssh_config = SshConfig::load(get_ssh_home()?.join("config"))?;
+    if let Some(host_name) = ssh_config
+        .find_host(host)
+        .and_then(|block| block.get("HostName"))
+    {
+        return Ok(host_name.to_string());
+    }
+
+    Ok(ProfileStore::load()?
+        .profiles
+        .values()
+        .map(Profile::to_git_init)
+        .find(|git_init| git_init.alias.is_some() && git_init.ssh_host() == host)
+        .map_or_else(|| host.to_string(), |git_init| git_init.host))
+}
+
+fn plan_set_remote(remote: &str, old: &RemoteUrl, new: RemoteUrl) -> Result<()> {
+    let actions = if *old == new {
+        vec![]
+    } else {
+        vec![PlannedAction::SetRemote {
+            remote: remote.to_string(),
+            old: old.to_string(),
+            new: new.to_string(),
+        }]
+    };
+    Plan::from(actions).run()
+}
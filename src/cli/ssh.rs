@@ -0,0 +1,97 @@
+use crate::*;
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Debug, Args)]
+pub struct SshConfigArgs {
+    /// SSH config file [default: ~/.ssh/config]
+    #[arg(long = "ssh-config", value_name = "SSH_CONFIG")]
+    pub path: Option<PathBuf>,
+}
+
+impl SshConfigArgs {
+    pub fn path(&self) -> Result<PathBuf> {
+        match &self.path {
+            Some(path) => Ok(path.clone()),
+            None => Ok(get_ssh_home()?.join("config")),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SshCommand {
+    /// Add or update the identity's host block in the SSH config
+    Add(Box<InitArgs>),
+    /// Print the block of a host
+    Show {
+        host: String,
+        #[command(flatten)]
+        config: SshConfigArgs,
+    },
+    /// Remove the block of a host
+    Remove {
+        host: String,
+        #[command(flatten)]
+        config: SshConfigArgs,
+    },
+    /// Report duplicate and shadowed Host blocks
+    Check {
+        #[command(flatten)]
+        config: SshConfigArgs,
+    },
+    /// Take away what others can do with the SSH directory, keys and config
+    Repair {
+        /// SSH directory [default: ~/.ssh]
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+impl SshCommand {
+    pub fn run(self) -> Result<()> {
+        match self {
+            Self::Add(args) => args.into_git_init()?.configure_ssh(),
+            Self::Show { host, config } => {
+                let ssh_config = SshConfig::load(config.path()?)?;
+                let block = ssh_config
+                    .find_host(&host)
+                    .ok_or_else(|| anyhow!("No Host block for '{}'", host))?;
+                println!("{}", block.header.raw);
+                block.lines.iter().for_each(|line| println!("{}", line.raw));
+                Ok(())
+            }
+            Self::Remove { host, config } => {
+                let path = config.path()?;
+                let mut ssh_config = SshConfig::load(&path)?;
+                if !ssh_config.remove_host(&host) {
+                    return Err(anyhow!("No Host block for '{}'", host));
+                }
+                Plan::from(ssh_config.plan_save(&path)?.into_iter().collect::<Vec<_>>()).run()
+            }
+            Self::Check { config } => {
+                let conflicts = SshConfig::load(config.path()?)?.conflicts();
+                conflicts
+                    .iter()
+                    .for_each(|conflict| println!("{}", conflict));
+                match conflicts.len() {
+                    0 => Ok(()),
+                    count => Err(anyhow!("Found {} SSH config conflicts", count)),
+                }
+            }
+            Self::Repair { dir } => {
+                let dir = match dir {
+                    Some(dir) => dir,
+                    None => get_ssh_home()?,
+                };
+                let plan = Plan::from(plan_repair_permissions(&dir));
+                print!("{}", plan);
+                if plan.is_empty() || is_dry_run() {
+                    Ok(())
+                } else {
+                    plan.apply()
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use crate::*;
+use anyhow::Result;
+use clap::Args;
+
+#[derive(Debug, Args)]
+#[command(
+    after_help = "Exit status: 11 pull, 12 reset, 13 status, 14 add, 15 commit, 16 push failed."
+)]
+pub struct UpArgs {
+    /// Commit message [default: Updating]
+    pub message: Vec<String>,
+}
+
+impl UpArgs {
+    pub fn run(self) -> Result<()> {
+        GitUp::new().with_message(self.message.join(" ")).run()
+    }
+}
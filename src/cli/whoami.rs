@@ -0,0 +1,17 @@
+use crate::*;
+use anyhow::Result;
+
+pub fn print_whoami() -> Result<()> {
+    println!(
+        "System User: {}@{}",
+        whoami::username(),
+        whoami::devicename()
+    );
+
+    for key in ["user.name", "user.email"] {
+        let value = get_git_value(key)?.unwrap_or_else(|| "<unset>".to_string());
+        println!("{}: {}", key, value);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,250 @@
+use crate::*;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt, path::PathBuf, str::FromStr};
+
+/// Something whose config entries can be compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Scope(GitConfig),
+    /// The local config of the repository at this path.
+    Repo(PathBuf),
+    Preset(String),
+}
+
+impl ConfigSource {
+    /// Every key with its values in order, multivars keeping all of theirs.
+    pub fn entries(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        let pairs: Vec<(String, String)> = match self {
+            Self::Scope(scope) => list_pairs(scope)?,
+            Self::Repo(path) => {
+                let git_dir = find_git_dir(&path.canonicalize()?)
+                    .ok_or_else(|| anyhow!("{} is not a git repository", path.display()))?;
+                list_pairs(&GitConfig::File(common_git_dir(&git_dir).join("config")))?
+            }
+            Self::Preset(name) => ConfigPreset::load(name)?
+                .resolved_entries(None)
+                .into_iter()
+                .map(|entry| Ok((ConfigKey::parse(&entry.key)?.to_string(), entry.value)))
+                .collect::<Result<_>>()?,
+        };
+
+        let mut entries: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (key, value) in pairs {
+            entries.entry(key).or_default().push(value);
+        }
+        Ok(entries)
+    }
+}
+
+fn list_pairs(scope: &GitConfig) -> Result<Vec<(String, String)>> {
+    Ok(scope
+        .list()?
+        .into_iter()
+        .map(|entry| {
+            let value = entry.value_str().to_string();
+            (entry.key, value)
+        })
+        .collect())
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scope(scope) => write!(f, "{}", scope),
+            Self::Repo(path) => write!(f, "repo:{}", path.display()),
+            Self::Preset(name) => write!(f, "preset:{}", name),
+        }
+    }
+}
+
+impl FromStr for ConfigSource {
+    type Err = anyhow::Error;
+
+    /// Accepts a scope (`global`, `file:<path>`, ...), `repo:<path>` or `preset:<name>`.
+    fn from_str(source: &str) -> Result<Self> {
+        if let Some(path) = source.strip_prefix("repo:") {
+            Ok(Self::Repo(PathBuf::from(path)))
+        } else if let Some(name) = source.strip_prefix("preset:") {
+            Ok(Self::Preset(name.to_string()))
+        } else {
+            source.parse::<GitConfig>().map(Self::Scope).map_err(|_| {
+                anyhow!(
+                    "Unknown config source '{}' (expected a scope, file:<path>, repo:<path> or preset:<name>)",
+                    source
+                )
+            })
+        }
+    }
+}
+
+/// How one key differs between two sources.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum ConfigChange {
+    Added {
+        key: String,
+        values: Vec<String>,
+    },
+    Removed {
+        key: String,
+        values: Vec<String>,
+    },
+    Changed {
+        key: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+}
+
+/// The keys added, removed and changed going from `left` to `right`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigDiff {
+    pub left: String,
+    pub right: String,
+    pub changes: Vec<ConfigChange>,
+}
+
+impl ConfigDiff {
+    pub fn between(left: &ConfigSource, right: &ConfigSource) -> Result<Self> {
+        Ok(Self {
+            left: left.to_string(),
+            right: right.to_string(),
+            changes: Self::compare(&left.entries()?, &right.entries()?),
+        })
+    }
+
+    pub fn compare(
+        left: &BTreeMap<String, Vec<String>>,
+        right: &BTreeMap<String, Vec<String>>,
+    ) -> Vec<ConfigChange> {
+        let mut keys: Vec<&String> = left.keys().chain(right.keys()).collect();
+        keys.sort();
+        keys.dedup();
+
+        keys.into_iter()
+            .filter_map(|key| match (left.get(key), right.get(key)) {
+                (None, Some(values)) => Some(ConfigChange::Added {
+                    key: key.clone(),
+                    values: values.clone(),
+                }),
+                (Some(values), None) => Some(ConfigChange::Removed {
+                    key: key.clone(),
+                    values: values.clone(),
+                }),
+                (Some(old), Some(new)) if old != new => Some(ConfigChange::Changed {
+                    key: key.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- {}\n+++ {}", self.left, self.right)?;
+        if self.is_empty() {
+            return writeln!(f, "No differences.");
+        }
+
+        for change in &self.changes {
+            match change {
+                ConfigChange::Added { key, values } => values
+                    .iter()
+                    .try_for_each(|value| writeln!(f, "+ {} = {}", key, value))?,
+                ConfigChange::Removed { key, values } => values
+                    .iter()
+                    .try_for_each(|value| writeln!(f, "- {} = {}", key, value))?,
+                ConfigChange::Changed { key, old, new } => match (old.as_slice(), new.as_slice()) {
+                    ([old], [new]) => writeln!(f, "~ {}: {} -> {}", key, old, new)?,
+                    _ => {
+                        writeln!(f, "~ {}", key)?;
+                        let removed: Vec<_> =
+                            old.iter().filter(|value| !new.contains(value)).collect();
+                        let added: Vec<_> =
+                            new.iter().filter(|value| !old.contains(value)).collect();
+                        removed
+                            .iter()
+                            .try_for_each(|value| writeln!(f, "    - {}", value))?;
+                        added
+                            .iter()
+                            .try_for_each(|value| writeln!(f, "    + {}", value))?;
+                        if removed.is_empty() && added.is_empty() {
+                            writeln!(f, "    (same values, different order or count)")?;
+                        }
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_compare_multivars() {
+    let entries = |pairs: &[(&str, &[&str])]| -> BTreeMap<String, Vec<String>> {
+        pairs
+            .iter()
+            .map(|(key, values)| {
+                (
+                    key.to_string(),
+                    values.iter().map(|value| value.to_string()).collect(),
+                )
+            })
+            .collect()
+    };
+    let left = entries(&[
+        ("user.name", &["Jane"]),
+        ("core.editor", &["vim"]),
+        ("remote.origin.fetch", &["a", "b"]),
+    ]);
+    let right = entries(&[
+        ("user.name", &["Jane Doe"]),
+        ("init.defaultbranch", &["main"]),
+        ("remote.origin.fetch", &["a", "c"]),
+    ]);
+
+    let changes = ConfigDiff::compare(&left, &right);
+    assert_eq!(changes.len(), 4);
+    assert_eq!(
+        changes[0],
+        ConfigChange::Removed {
+            key: "core.editor".to_string(),
+            values: vec!["vim".to_string()]
+        }
+    );
+    assert!(matches!(&changes[1], ConfigChange::Added { key, .. } if key == "init.defaultbranch"));
+
+    let diff = ConfigDiff {
+        left: "global".to_string(),
+        right: "local".to_string(),
+        changes,
+    };
+    let rendered = diff.to_string();
+    assert!(rendered.contains("~ remote.origin.fetch\n    - b\n    + c\n"));
+    assert!(rendered.contains("~ user.name: Jane -> Jane Doe\n"));
+}
+
+#[test]
+fn test_parse_source() {
+    assert_eq!(
+        "global".parse::<ConfigSource>().unwrap(),
+        ConfigSource::Scope(GitConfig::Global)
+    );
+    assert_eq!(
+        "repo:../other".parse::<ConfigSource>().unwrap(),
+        ConfigSource::Repo(PathBuf::from("../other"))
+    );
+    assert_eq!(
+        "preset:baseline".parse::<ConfigSource>().unwrap(),
+        ConfigSource::Preset("baseline".to_string())
+    );
+    assert!("elsewhere".parse::<ConfigSource>().is_err());
+}
@@ -0,0 +1,318 @@
+use crate::*;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs::read_to_string, path::Path, str::FromStr};
+
+/// The file formats a [`ConfigDocument`] is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigDocumentFormat {
+    #[default]
+    Toml,
+    Json,
+}
+
+impl ConfigDocumentFormat {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Toml => "toml",
+            Self::Json => "json",
+        }
+    }
+
+    /// Guesses the format from the file extension, defaulting to TOML.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::Json,
+            _ => Self::Toml,
+        }
+    }
+}
+
+impl FromStr for ConfigDocumentFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format.to_lowercase().as_str() {
+            "toml" => Ok(Self::Toml),
+            "json" => Ok(Self::Json),
+            _ => Err(anyhow!(
+                "Unknown format '{}' (expected toml or json)",
+                format
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentEntry {
+    pub key: String,
+    /// `None` for a key without a value, such as `[core] bare`, which git reads as `true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// A portable copy of one scope's config, with paths under `$HOME` written as `~`.
+/// Multivar keys appear once per value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigDocument {
+    pub scope: GitConfig,
+    #[serde(default)]
+    pub entries: Vec<DocumentEntry>,
+}
+
+impl ConfigDocument {
+    pub fn export(scope: &GitConfig) -> Result<Self> {
+        let home = dirs::home_dir().map(|home| home.display().to_string());
+        let portable = |text: &str| match &home {
+            Some(home) => contract_home(text, home),
+            None => text.to_string(),
+        };
+
+        Ok(Self {
+            scope: scope.clone(),
+            entries: scope
+                .list()?
+                .iter()
+                .map(|entry| DocumentEntry {
+                    key: portable(&entry.key),
+                    value: entry.value.as_deref().map(portable),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn parse(content: &str, format: ConfigDocumentFormat) -> Result<Self> {
+        match format {
+            ConfigDocumentFormat::Toml => Ok(toml::from_str(content)?),
+            ConfigDocumentFormat::Json => Ok(serde_json::from_str(content)?),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content =
+            read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content, ConfigDocumentFormat::from_path(path))
+            .with_context(|| format!("Invalid config document {}", path.display()))
+    }
+
+    pub fn render(&self, format: ConfigDocumentFormat) -> Result<String> {
+        match format {
+            ConfigDocumentFormat::Toml => Ok(toml::to_string(self)?),
+            ConfigDocumentFormat::Json => Ok(serde_json::to_string_pretty(self)? + "\n"),
+        }
+    }
+
+    /// The changes replaying the document into `scope` would make. Keys set once replace
+    /// the current value, and a different current value is a conflict; keys set several
+    /// times are multivars whose missing values are added.
+    pub fn plan_import(&self, scope: &GitConfig) -> Result<Plan> {
+        let mut values: BTreeMap<&str, Vec<Option<&str>>> = BTreeMap::new();
+        let mut order = vec![];
+        for entry in &self.entries {
+            let slot = values.entry(&entry.key).or_default();
+            if slot.is_empty() {
+                order.push(entry.key.as_str());
+            }
+            slot.push(entry.value.as_deref());
+        }
+
+        let mut actions = vec![];
+        for key in order {
+            match values[key].as_slice() {
+                [None] => {
+                    let current = scope.list()?;
+                    let mut entries = current.iter().filter(|entry| entry.key == key);
+                    match (entries.next(), entries.next()) {
+                        (Some(entry), None) if entry.value.is_none() => {}
+                        _ => actions.push(PlannedAction::SetBareConfig {
+                            scope: scope.clone(),
+                            key: key.to_string(),
+                            old: scope.get_value(key)?,
+                        }),
+                    }
+                }
+                [Some(value)] => {
+                    let old = scope.get_value(key)?;
+                    if old.as_deref() != Some(*value) {
+                        actions.push(PlannedAction::SetConfig {
+                            scope: scope.clone(),
+                            key: key.to_string(),
+                            old,
+                            new: value.to_string(),
+                        });
+                    }
+                }
+                values => {
+                    let current = scope.get_all(key)?;
+                    for value in values.iter().flatten() {
+                        if !current.iter().any(|current| current == value) {
+                            actions.push(PlannedAction::AddConfig {
+                                scope: scope.clone(),
+                                key: key.to_string(),
+                                value: value.to_string(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Plan::from(actions))
+    }
+}
+
+/// The keys an import would overwrite with a different value.
+pub fn import_conflicts(plan: &Plan) -> Vec<(String, String, String)> {
+    plan.actions
+        .iter()
+        .filter_map(|action| match action {
+            PlannedAction::SetConfig {
+                key,
+                old: Some(old),
+                new,
+                ..
+            } => Some((key.clone(), old.clone(), new.clone())),
+            PlannedAction::SetBareConfig {
+                key,
+                old: Some(old),
+                ..
+            } => Some((key.clone(), old.clone(), "<no value>".to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Rewrites every path starting with `home` as `~`, wherever a path can start.
+fn contract_home(text: &str, home: &str) -> String {
+    let home = home.trim_end_matches('/');
+    if home.is_empty() {
+        return text.to_string();
+    }
+
+    let mut contracted = String::new();
+    let mut rest = text;
+    while let Some(index) = rest.find(home) {
+        let after = &rest[index + home.len()..];
+        let starts_path = rest[..index]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || matches!(c, '=' | ':' | '"' | '\''));
+        let ends_path = after.is_empty() || after.starts_with(['/', ' ', '"', '\'']);
+
+        contracted.push_str(&rest[..index]);
+        if starts_path && ends_path {
+            contracted.push('~');
+        } else {
+            contracted.push_str(home);
+        }
+        rest = after;
+    }
+
+    contracted.push_str(rest);
+    contracted
+}
+
+#[test]
+fn test_contract_home() {
+    let home = "/home/jane";
+    assert_eq!(
+        contract_home("/home/jane/.config/gitsy/work.gitconfig", home),
+        "~/.config/gitsy/work.gitconfig"
+    );
+    assert_eq!(
+        contract_home("includeif.gitdir:/home/jane/work/.path", home),
+        "includeif.gitdir:~/work/.path"
+    );
+    assert_eq!(
+        contract_home("ssh -i /home/jane/.ssh/id -F /home/janet/x", home),
+        "ssh -i ~/.ssh/id -F /home/janet/x"
+    );
+}
+
+#[test]
+fn test_document_round_trip() {
+    let document = ConfigDocument {
+        scope: GitConfig::Global,
+        entries: vec![
+            DocumentEntry {
+                key: "user.name".to_string(),
+                value: Some("Jane Doe".to_string()),
+            },
+            DocumentEntry {
+                key: "remote.origin.fetch".to_string(),
+                value: Some("+refs/heads/*:refs/remotes/origin/*".to_string()),
+            },
+            DocumentEntry {
+                key: "core.bare".to_string(),
+                value: None,
+            },
+        ],
+    };
+
+    for format in [ConfigDocumentFormat::Toml, ConfigDocumentFormat::Json] {
+        let rendered = document.render(format).unwrap();
+        assert_eq!(ConfigDocument::parse(&rendered, format).unwrap(), document);
+    }
+    assert!(document
+        .render(ConfigDocumentFormat::Toml)
+        .unwrap()
+        .starts_with("scope = \"global\"\n"));
+}
+
+#[test]
+fn test_plan_import_reports_conflicts() {
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("config");
+    std::fs::write(
+        &path,
+        "[user]\n\tname = Jane\n\temail = old@example.com\n[remote \"origin\"]\n\tfetch = a\n[core]\n\tbare\n",
+    )
+    .unwrap();
+    let scope = GitConfig::File(path.clone());
+
+    let entry = |key: &str, value: &str| DocumentEntry {
+        key: key.to_string(),
+        value: Some(value.to_string()),
+    };
+    let bare = |key: &str| DocumentEntry {
+        key: key.to_string(),
+        value: None,
+    };
+    let document = ConfigDocument {
+        scope: GitConfig::Global,
+        entries: vec![
+            entry("user.name", "Jane"),
+            entry("user.email", "new@example.com"),
+            entry("remote.origin.fetch", "a"),
+            entry("remote.origin.fetch", "b"),
+            bare("core.bare"),
+            bare("core.logallrefupdates"),
+        ],
+    };
+
+    let plan = document.plan_import(&scope).unwrap();
+    assert_eq!(plan.actions.len(), 3);
+    assert_eq!(
+        import_conflicts(&plan),
+        vec![(
+            "user.email".to_string(),
+            "old@example.com".to_string(),
+            "new@example.com".to_string()
+        )]
+    );
+    assert!(matches!(
+        &plan.actions[1],
+        PlannedAction::AddConfig { value, .. } if value == "b"
+    ));
+    assert!(matches!(
+        &plan.actions[2],
+        PlannedAction::SetBareConfig { key, old: None, .. } if key == "core.logallrefupdates"
+    ));
+
+    //> The bare key goes back without a value, so that git still reads it as true
+    plan.actions[2].apply().unwrap();
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .ends_with("[core]\n\tbare\n\tlogallrefupdates\n"));
+    assert_eq!(document.plan_import(&scope).unwrap().actions.len(), 2);
+}
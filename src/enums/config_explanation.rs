@@ -0,0 +1,223 @@
+use crate::*;
+use anyhow::Result;
+use serde::Serialize;
+use std::{fmt, path::PathBuf};
+
+/// An `include.path` or `includeIf.<condition>.path` entry and whether git follows it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IncludeStatus {
+    pub scope: GitConfig,
+    /// The file holding the include entry.
+    pub file: PathBuf,
+    pub line: usize,
+    /// `None` for an unconditional `include.path`.
+    pub condition: Option<String>,
+    pub path: PathBuf,
+    pub matched: bool,
+    pub exists: bool,
+    /// Whether the included file sets the explained key.
+    pub sets_key: bool,
+}
+
+/// Where a key is set across every scope and included file, and which value wins.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigExplanation {
+    pub key: String,
+    /// Every value of the key in the order git reads them; the last one wins.
+    pub values: Vec<GitConfigEntry>,
+    pub includes: Vec<IncludeStatus>,
+}
+
+impl ConfigExplanation {
+    /// Explains `key` for the repository around the current directory.
+    pub fn resolve(key: &str) -> Result<Self> {
+        let files: Vec<(GitConfig, PathBuf)> = GitConfig::all()
+            .into_iter()
+            .flat_map(|scope| {
+                scope
+                    .native_files()
+                    .into_iter()
+                    .map(move |path| (scope.clone(), path))
+            })
+            .collect();
+
+        Self::collect(
+            key,
+            GitConfig::list_all()?,
+            &files,
+            &IncludeContext::discover(),
+        )
+    }
+
+    /// Explains `key` from already listed `entries` and the scope `files` they were read from.
+    pub fn collect(
+        key: &str,
+        entries: Vec<GitConfigEntry>,
+        files: &[(GitConfig, PathBuf)],
+        context: &IncludeContext,
+    ) -> Result<Self> {
+        let key = ConfigKey::parse(key)?.to_string();
+        let values = entries
+            .into_iter()
+            .filter(|entry| entry.key == key)
+            .collect();
+
+        let mut includes = vec![];
+        for (scope, path) in files.iter().filter(|(_, path)| path.exists()) {
+            for entry in GitConfigFile::load(path)?.resolve(context)? {
+                let (Some(condition), Some(value)) = (include_condition(&entry.key), &entry.value)
+                else {
+                    continue;
+                };
+                let target = include_path(value, &entry.file);
+                let sets_key = GitConfigFile::load(&target)
+                    .and_then(|file| file.get_all(&key))
+                    .is_ok_and(|values| !values.is_empty());
+
+                includes.push(IncludeStatus {
+                    scope: scope.clone(),
+                    matched: condition
+                        .is_none_or(|condition| context.matches(condition, &entry.file)),
+                    condition: condition.map(str::to_string),
+                    exists: target.exists(),
+                    file: entry.file,
+                    line: entry.line,
+                    path: target,
+                    sets_key,
+                });
+            }
+        }
+
+        Ok(Self {
+            key,
+            values,
+            includes,
+        })
+    }
+
+    /// The value `git config --get` returns.
+    pub fn winner(&self) -> Option<&GitConfigEntry> {
+        self.values.last()
+    }
+
+    /// Values overridden by the winner, or extra values of a multivar.
+    pub fn shadowed(&self) -> &[GitConfigEntry] {
+        match self.values.split_last() {
+            Some((_, shadowed)) => shadowed,
+            None => &[],
+        }
+    }
+}
+
+impl fmt::Display for ConfigExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.key)?;
+
+        for scope in GitConfig::all() {
+            let values: Vec<_> = self
+                .values
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.scope == scope)
+                .collect();
+            if values.is_empty() {
+                writeln!(f, "  {:<9} <unset>", scope.to_str())?;
+            }
+            for (index, entry) in values {
+                let origin = match (&entry.origin, entry.line) {
+                    (Some(origin), Some(line)) => format!("{}:{}", tilde_path(origin), line),
+                    (Some(origin), None) => tilde_path(origin),
+                    (None, _) => "-".to_string(),
+                };
+                let status = if index + 1 == self.values.len() {
+                    "wins"
+                } else {
+                    "shadowed"
+                };
+                writeln!(
+                    f,
+                    "  {:<9} {:<8} {}  ({})",
+                    scope.to_str(),
+                    status,
+                    entry.value_str(),
+                    origin
+                )?;
+            }
+        }
+
+        if !self.includes.is_empty() {
+            writeln!(f, "\nIncludes:")?;
+        }
+        for include in &self.includes {
+            let condition = match &include.condition {
+                Some(condition) => format!("includeIf \"{}\"", condition),
+                None => "include".to_string(),
+            };
+            let status = match (include.matched, include.exists) {
+                (false, _) => "not matched",
+                (true, false) => "matched, file missing",
+                (true, true) => "matched",
+            };
+            let sets_key = if include.sets_key {
+                format!(", sets {}", self.key)
+            } else {
+                String::new()
+            };
+            writeln!(
+                f,
+                "  {}:{}  {} -> {}  [{}{}]",
+                tilde_path(&include.file),
+                include.line,
+                condition,
+                tilde_path(&include.path),
+                status,
+                sets_key
+            )?;
+        }
+
+        if self.values.is_empty() {
+            writeln!(f, "\n{} is not set anywhere.", self.key)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_explain_includes() {
+    let temp = tempfile::tempdir().unwrap();
+    let global = temp.path().join("gitconfig");
+    let work = temp.path().join("work.gitconfig");
+    let home = temp.path().join("home.gitconfig");
+    std::fs::write(&work, "[user]\n\temail = jane@work.example\n").unwrap();
+    std::fs::write(&home, "[user]\n\temail = jane@home.example\n").unwrap();
+    std::fs::write(
+        &global,
+        "[user]\n\temail = jane@example.com\n[includeIf \"gitdir:/srv/work/\"]\n\tpath = work.gitconfig\n[includeIf \"gitdir:/srv/home/\"]\n\tpath = home.gitconfig\n",
+    )
+    .unwrap();
+
+    let context = IncludeContext {
+        git_dir: Some(PathBuf::from("/srv/work/app/.git")),
+        branch: None,
+    };
+    let entries = GitConfigFile::load(&global)
+        .unwrap()
+        .resolve(&context)
+        .unwrap()
+        .into_iter()
+        .map(|entry| GitConfigEntry::from_resolved(entry, &GitConfig::Global))
+        .collect();
+    let files = [(GitConfig::Global, global.clone())];
+    let explanation = ConfigExplanation::collect("User.Email", entries, &files, &context).unwrap();
+
+    assert_eq!(explanation.key, "user.email");
+    assert_eq!(
+        explanation.winner().unwrap().value_str(),
+        "jane@work.example"
+    );
+    assert_eq!(explanation.shadowed().len(), 1);
+    assert_eq!(explanation.includes.len(), 2);
+    assert!(explanation.includes[0].matched && explanation.includes[0].sets_key);
+    assert!(!explanation.includes[1].matched && explanation.includes[1].sets_key);
+    assert!(explanation.to_string().contains("system    <unset>"));
+}
@@ -0,0 +1,503 @@
+use crate::*;
+use anyhow::Result;
+use serde::Serialize;
+use std::{fmt, fs::read_to_string, path::PathBuf, process::Command};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl LintSeverity {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// A problem `gitsy config lint` found in the config in effect.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintFinding {
+    pub rule: &'static str,
+    pub severity: LintSeverity,
+    pub key: String,
+    /// `path:line` of the offending entry; `None` when the problem is a missing key.
+    pub location: Option<String>,
+    pub message: String,
+    /// Why the finding matters.
+    pub explanation: String,
+    /// The config changes that fix it, when they can be made without guessing.
+    #[serde(serialize_with = "serialize_fix")]
+    pub fix: Option<Plan>,
+}
+
+impl LintFinding {
+    /// Lints every scope, includes followed.
+    pub fn check_all() -> Result<Vec<Self>> {
+        Ok(Self::check(&GitConfig::list_all()?))
+    }
+
+    /// Runs every rule over `entries`, which are in the order git reads them.
+    pub fn check(entries: &[GitConfigEntry]) -> Vec<Self> {
+        let mut findings = vec![];
+        findings.extend(missing_identity(entries));
+        findings.extend(signing_identity(entries));
+        findings.extend(autocrlf_on_linux(entries));
+        findings.extend(missing_editor(entries));
+        findings.extend(duplicate_values(entries));
+        findings.extend(insecure_instead_of(entries));
+        findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+        findings
+    }
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} [{}]",
+            self.severity.to_str(),
+            self.message,
+            self.rule
+        )?;
+        if let Some(location) = &self.location {
+            writeln!(f, "    at {}", location)?;
+        }
+        writeln!(f, "    {}", self.explanation)?;
+        if let Some(fix) = &self.fix {
+            fix.actions
+                .iter()
+                .try_for_each(|action| writeln!(f, "    fix: {}", action.to_string().trim_end()))?;
+        }
+        Ok(())
+    }
+}
+
+/// The entry that wins for `key`, the last one git reads.
+fn effective<'a>(entries: &'a [GitConfigEntry], key: &str) -> Option<&'a GitConfigEntry> {
+    entries.iter().rfind(|entry| entry.key == key)
+}
+
+/// The config a fix to `entry` has to edit: its scope, or the included file it came from.
+fn fix_scope(entry: &GitConfigEntry) -> GitConfig {
+    let canonical = |path: &PathBuf| path.canonicalize().unwrap_or_else(|_| path.clone());
+    match (&entry.origin, entry.scope.path()) {
+        (Some(origin), Ok(path)) if canonical(origin) == canonical(&path) => entry.scope.clone(),
+        (Some(origin), _) => GitConfig::File(origin.clone()),
+        (None, _) => entry.scope.clone(),
+    }
+}
+
+fn location(entry: &GitConfigEntry) -> Option<String> {
+    entry.origin.as_ref().map(|origin| match entry.line {
+        Some(line) => format!("{}:{}", tilde_path(origin), line),
+        None => tilde_path(origin),
+    })
+}
+
+/// The values `fix_scope(entry)` holds for the entry's key, as far as `entries` shows.
+fn values_in_file<'a>(entries: &'a [GitConfigEntry], entry: &GitConfigEntry) -> Vec<&'a str> {
+    entries
+        .iter()
+        .filter(|other| other.key == entry.key && other.origin == entry.origin)
+        .map(GitConfigEntry::value_str)
+        .collect()
+}
+
+/// Removes some values of the entry's key, keeping the other values of a multivar.
+fn remove_values(entries: &[GitConfigEntry], entry: &GitConfigEntry, remove: &[&str]) -> Plan {
+    let scope = fix_scope(entry);
+    let values = values_in_file(entries, entry);
+    if values.len() == 1 {
+        return Plan::from(vec![PlannedAction::UnsetConfig {
+            scope,
+            key: entry.key.clone(),
+            old: entry.value_str().to_string(),
+        }]);
+    }
+
+    let mut actions = vec![PlannedAction::UnsetAllConfig {
+        scope: scope.clone(),
+        key: entry.key.clone(),
+        old: values.iter().map(|value| value.to_string()).collect(),
+    }];
+    actions.extend(
+        values
+            .iter()
+            .filter(|value| !remove.contains(value))
+            .map(|value| PlannedAction::AddConfig {
+                scope: scope.clone(),
+                key: entry.key.clone(),
+                value: value.to_string(),
+            }),
+    );
+    Plan::from(actions)
+}
+
+fn missing_identity(entries: &[GitConfigEntry]) -> Vec<LintFinding> {
+    ["user.name", "user.email"]
+        .into_iter()
+        .filter(|key| effective(entries, key).is_none_or(|entry| entry.value_str().is_empty()))
+        .map(|key| LintFinding {
+            rule: "missing-identity",
+            severity: LintSeverity::Error,
+            key: key.to_string(),
+            location: None,
+            message: format!("{} is not set in any scope", key),
+            explanation: format!(
+                "Git refuses to commit without it, or makes one up from the user and host names. \
+                 Set it with `gitsy config set --scope global {} <value>`.",
+                key
+            ),
+            fix: None,
+        })
+        .collect()
+}
+
+/// The email addresses in an identity such as `Jane Doe <jane@example.com>`.
+fn identity_emails(identity: &str) -> Vec<String> {
+    identity
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, '<' | '>' | ',' | '(' | ')' | '"')))
+        .filter(|word| word.contains('@'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The identities attached to the signing key, or `Err` with why they could not be read.
+/// `Ok(None)` when the key format is one gitsy does not inspect.
+fn signing_key_identities(format: &str, key: &str) -> Result<Option<Vec<String>>, String> {
+    match format {
+        "ssh" => {
+            let public_key = match key.strip_prefix("key::") {
+                Some(literal) => literal.to_string(),
+                None => {
+                    let path = expand_tilde(key);
+                    let public_path = match path.extension() {
+                        Some(extension) if extension == "pub" => path,
+                        _ => PathBuf::from(format!("{}.pub", path.display())),
+                    };
+                    read_to_string(&public_path)
+                        .map_err(|_| format!("{} cannot be read", tilde_path(&public_path)))?
+                }
+            };
+            let comment: Vec<&str> = public_key.split_whitespace().skip(2).collect();
+            Ok(Some(vec![comment.join(" ")]))
+        }
+        "openpgp" => {
+            let Ok(output) = Command::new("gpg")
+                .args(["--list-keys", "--with-colons", key])
+                .output()
+            else {
+                return Ok(None);
+            };
+            if !output.status.success() {
+                return Err(format!("gpg has no key '{}'", key));
+            }
+            Ok(Some(
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .filter(|line| line.starts_with("uid:"))
+                    .filter_map(|line| line.split(':').nth(9).map(str::to_string))
+                    .collect(),
+            ))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn signing_identity(entries: &[GitConfigEntry]) -> Vec<LintFinding> {
+    let (Some(key), Some(email)) = (
+        effective(entries, "user.signingkey"),
+        effective(entries, "user.email"),
+    ) else {
+        return vec![];
+    };
+    let format = effective(entries, "gpg.format").map_or("openpgp", GitConfigEntry::value_str);
+
+    let (message, explanation) = match signing_key_identities(format, key.value_str()) {
+        Ok(None) => return vec![],
+        Err(problem) => (
+            format!("the {} signing key is unusable: {}", format, problem),
+            "Signed commits and tags fail until user.signingkey points at a key that exists."
+                .to_string(),
+        ),
+        Ok(Some(identities)) => {
+            let emails: Vec<String> = identities
+                .iter()
+                .flat_map(|identity| identity_emails(identity))
+                .collect();
+            if emails.is_empty()
+                || emails
+                    .iter()
+                    .any(|key_email| key_email.eq_ignore_ascii_case(email.value_str()))
+            {
+                return vec![];
+            }
+            (
+                format!(
+                    "user.email '{}' does not match the signing key's identity ({})",
+                    email.value_str(),
+                    emails.join(", ")
+                ),
+                "Hosts only show signatures as verified when the key belongs to the committer email."
+                    .to_string(),
+            )
+        }
+    };
+
+    vec![LintFinding {
+        rule: "signing-identity",
+        severity: LintSeverity::Warning,
+        key: key.key.clone(),
+        location: location(key),
+        message,
+        explanation,
+        fix: None,
+    }]
+}
+
+fn is_true(value: Option<&str>) -> bool {
+    match value {
+        None => true,
+        Some(value) => matches!(value.to_lowercase().as_str(), "true" | "yes" | "on" | "1"),
+    }
+}
+
+fn autocrlf_on_linux(entries: &[GitConfigEntry]) -> Vec<LintFinding> {
+    if !cfg!(target_os = "linux") {
+        return vec![];
+    }
+    let Some(entry) = effective(entries, "core.autocrlf") else {
+        return vec![];
+    };
+    if !is_true(entry.value.as_deref()) {
+        return vec![];
+    }
+
+    vec![LintFinding {
+        rule: "autocrlf-on-linux",
+        severity: LintSeverity::Warning,
+        key: entry.key.clone(),
+        location: location(entry),
+        message: "core.autocrlf is true on Linux".to_string(),
+        explanation: "It checks files out with CRLF line endings, which Linux tools do not expect; 'input' only normalises on commit.".to_string(),
+        fix: Some(Plan::from(vec![PlannedAction::SetConfig {
+            scope: fix_scope(entry),
+            key: entry.key.clone(),
+            old: entry.value.clone(),
+            new: "input".to_string(),
+        }])),
+    }]
+}
+
+/// The program a shell command runs, such as `code` for `"code" --wait`.
+fn command_program(command: &str) -> &str {
+    let command = command.trim_start();
+    match command.strip_prefix(['"', '\'']) {
+        Some(rest) => {
+            let quote = &command[..1];
+            rest.split(quote).next().unwrap_or_default()
+        }
+        None => command.split_whitespace().next().unwrap_or_default(),
+    }
+}
+
+fn missing_editor(entries: &[GitConfigEntry]) -> Vec<LintFinding> {
+    let Some(entry) = effective(entries, "core.editor") else {
+        return vec![];
+    };
+    let program = command_program(entry.value_str());
+    if program.is_empty() || find_executable(program).is_some() {
+        return vec![];
+    }
+
+    vec![LintFinding {
+        rule: "missing-editor",
+        severity: LintSeverity::Warning,
+        key: entry.key.clone(),
+        location: location(entry),
+        message: format!("core.editor runs '{}', which is not on PATH", program),
+        explanation: "Commits without -m, rebases and merges fail to open an editor; without core.editor git falls back to $VISUAL, $EDITOR or vi.".to_string(),
+        fix: Some(remove_values(entries, entry, &[entry.value_str()])),
+    }]
+}
+
+fn duplicate_values(entries: &[GitConfigEntry]) -> Vec<LintFinding> {
+    let mut findings = vec![];
+    let mut seen: Vec<(&str, &Option<PathBuf>)> = vec![];
+
+    for entry in entries {
+        if seen.contains(&(entry.key.as_str(), &entry.origin)) {
+            continue;
+        }
+        seen.push((&entry.key, &entry.origin));
+
+        let values = values_in_file(entries, entry);
+        let mut unique: Vec<&str> = vec![];
+        values.iter().for_each(|value| {
+            if !unique.contains(value) {
+                unique.push(value);
+            }
+        });
+        if unique.len() == values.len() {
+            continue;
+        }
+
+        let scope = fix_scope(entry);
+        let mut actions = vec![PlannedAction::UnsetAllConfig {
+            scope: scope.clone(),
+            key: entry.key.clone(),
+            old: values.iter().map(|value| value.to_string()).collect(),
+        }];
+        actions.extend(unique.iter().map(|value| PlannedAction::AddConfig {
+            scope: scope.clone(),
+            key: entry.key.clone(),
+            value: value.to_string(),
+        }));
+
+        findings.push(LintFinding {
+            rule: "duplicate-values",
+            severity: LintSeverity::Info,
+            key: entry.key.clone(),
+            location: location(entry),
+            message: format!(
+                "{} holds {} values, only {} of them distinct",
+                entry.key,
+                values.len(),
+                unique.len()
+            ),
+            explanation: "Repeated values of a multivar are applied twice and usually come from re-running a setup script.".to_string(),
+            fix: Some(Plan::from(actions)),
+        });
+    }
+    findings
+}
+
+fn is_ssh_url(url: &str) -> bool {
+    ["ssh://", "git+ssh://", "ssh+git://"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+        || (!url.contains("://") && url.split('/').next().is_some_and(|host| host.contains(':')))
+}
+
+fn insecure_instead_of(entries: &[GitConfigEntry]) -> Vec<LintFinding> {
+    let mut findings = vec![];
+    let mut seen: Vec<(&str, &Option<PathBuf>)> = vec![];
+
+    for entry in entries {
+        let Some(rest) = entry.key.strip_prefix("url.") else {
+            continue;
+        };
+        let Some(base) = rest
+            .strip_suffix(".insteadof")
+            .or_else(|| rest.strip_suffix(".pushinsteadof"))
+        else {
+            continue;
+        };
+        let Some(scheme) = ["http://", "git://"]
+            .into_iter()
+            .find(|scheme| base.starts_with(scheme))
+        else {
+            continue;
+        };
+        if seen.contains(&(entry.key.as_str(), &entry.origin)) {
+            continue;
+        }
+        seen.push((&entry.key, &entry.origin));
+
+        //> One finding per key and file, so that a single fix removes every SSH prefix
+        let insecure: Vec<&str> = values_in_file(entries, entry)
+            .into_iter()
+            .filter(|value| is_ssh_url(value))
+            .collect();
+        if insecure.is_empty() {
+            continue;
+        }
+
+        findings.push(LintFinding {
+            rule: "insecure-insteadof",
+            severity: LintSeverity::Error,
+            key: entry.key.clone(),
+            location: location(entry),
+            message: format!(
+                "remotes starting with '{}' are rewritten to '{}'",
+                insecure.join("', '"),
+                base
+            ),
+            explanation: format!(
+                "This turns SSH remotes into {} ones, which are neither encrypted nor authenticated.",
+                scheme.trim_end_matches("://")
+            ),
+            fix: Some(remove_values(entries, entry, &insecure)),
+        });
+    }
+    findings
+}
+
+#[test]
+fn test_lint_rules() {
+    let origin = Some(PathBuf::from("/nonexistent/gitsy-lint/config"));
+    let entry = |key: &str, value: &str| GitConfigEntry {
+        key: key.to_string(),
+        value: Some(value.to_string()),
+        scope: GitConfig::Global,
+        origin: origin.clone(),
+        line: Some(1),
+    };
+    let entries = vec![
+        entry("user.email", "jane@example.com"),
+        entry("core.editor", "\"gitsy-surely-missing-editor\" --wait"),
+        entry("remote.origin.fetch", "a"),
+        entry("remote.origin.fetch", "b"),
+        entry("remote.origin.fetch", "a"),
+        entry("url.http://example.com/.insteadof", "git@example.com:"),
+        entry("url.https://example.com/.insteadof", "git@example.com:"),
+    ];
+
+    let findings = LintFinding::check(&entries);
+    let rules: Vec<&str> = findings.iter().map(|finding| finding.rule).collect();
+    assert_eq!(
+        rules,
+        [
+            "missing-identity",
+            "insecure-insteadof",
+            "missing-editor",
+            "duplicate-values"
+        ]
+    );
+    assert_eq!(findings[0].key, "user.name");
+    assert!(findings[0].fix.is_none());
+    assert_eq!(findings[1].key, "url.http://example.com/.insteadof");
+
+    let duplicates = findings[3].fix.as_ref().unwrap();
+    assert_eq!(duplicates.actions.len(), 3);
+    assert!(matches!(
+        &duplicates.actions[0],
+        PlannedAction::UnsetAllConfig { scope: GitConfig::File(_), old, .. } if old.len() == 3
+    ));
+}
+
+#[test]
+fn test_identity_parsing() {
+    assert_eq!(
+        identity_emails("Jane Doe <jane@example.com>"),
+        ["jane@example.com"]
+    );
+    assert!(identity_emails("jane@laptop.local ").len() == 1);
+    assert!(identity_emails("work key").is_empty());
+    assert_eq!(
+        command_program("'/opt/My Editor/bin/edit' -w"),
+        "/opt/My Editor/bin/edit"
+    );
+    assert_eq!(command_program("vim"), "vim");
+    assert!(is_ssh_url("git@github.com:"));
+    assert!(is_ssh_url("ssh://git@github.com/"));
+    assert!(!is_ssh_url("https://github.com/"));
+}
@@ -0,0 +1,202 @@
+use crate::*;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fs::{read_dir, read_to_string},
+    path::PathBuf,
+};
+
+/// Presets built into gitsy; a user preset file with the same name takes precedence.
+const SHIPPED_PRESETS: [(&str, &str); 1] =
+    [("baseline", include_str!("../../presets/baseline.toml"))];
+
+/// A key to set when a preset is applied. `${VAR}` in the value expands from the environment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresetEntry {
+    pub key: String,
+    pub value: String,
+    #[serde(default = "default_scope")]
+    pub scope: GitConfig,
+}
+
+fn default_scope() -> GitConfig {
+    GitConfig::Local
+}
+
+/// Where a preset was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresetSource {
+    Shipped,
+    File(PathBuf),
+}
+
+/// A named set of config entries, such as the team baseline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigPreset {
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip)]
+    pub source: Option<PresetSource>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub entries: Vec<PresetEntry>,
+}
+
+impl ConfigPreset {
+    /// Directory holding user presets, one `<name>.toml` file each.
+    pub fn presets_dir() -> Result<PathBuf> {
+        Ok(get_config_dir()?.join("presets"))
+    }
+
+    pub fn parse(name: &str, content: &str) -> Result<Self> {
+        let mut preset: Self =
+            toml::from_str(content).with_context(|| format!("Invalid preset '{}'", name))?;
+        preset.name = name.to_string();
+        Ok(preset)
+    }
+
+    /// Loads a user preset, falling back to the shipped one of the same name.
+    pub fn load(name: &str) -> Result<Self> {
+        let path = Self::presets_dir()?.join(format!("{}.toml", name));
+        if path.exists() {
+            let content = read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let mut preset = Self::parse(name, &content)?;
+            preset.source = Some(PresetSource::File(path));
+            return Ok(preset);
+        }
+
+        match SHIPPED_PRESETS.iter().find(|(shipped, _)| *shipped == name) {
+            Some((_, content)) => {
+                let mut preset = Self::parse(name, content)?;
+                preset.source = Some(PresetSource::Shipped);
+                Ok(preset)
+            }
+            None => Err(anyhow!(
+                "No preset named '{}' (see `gitsy config presets`)",
+                name
+            )),
+        }
+    }
+
+    /// Every available preset by name, user presets shadowing shipped ones.
+    pub fn list() -> Result<Vec<Self>> {
+        let mut names: Vec<String> = SHIPPED_PRESETS
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+
+        let dir = Self::presets_dir()?;
+        if dir.is_dir() {
+            for entry in read_dir(&dir)? {
+                let path = entry?.path();
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "toml")
+                {
+                    if let Some(stem) = path.file_stem() {
+                        names.push(stem.to_string_lossy().to_string());
+                    }
+                }
+            }
+        }
+        names.sort();
+        names.dedup();
+
+        names.iter().map(|name| Self::load(name)).collect()
+    }
+
+    /// The entries with their values expanded; `scope` overrides the scope of every entry.
+    /// Entries referring to unset environment variables are skipped with a warning.
+    pub fn resolved_entries(&self, scope: Option<&GitConfig>) -> Vec<PresetEntry> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match expand_env(&entry.value) {
+                Ok(value) => Some(PresetEntry {
+                    key: entry.key.clone(),
+                    value,
+                    scope: scope.cloned().unwrap_or_else(|| entry.scope.clone()),
+                }),
+                Err(error) => {
+                    eprintln!(
+                        "Skipping '{}' from the preset '{}': {}",
+                        entry.key, self.name, error
+                    );
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The config changes applying the preset would make, skipping keys already in place.
+    pub fn plan(&self, scope: Option<&GitConfig>) -> Result<Plan> {
+        let mut actions = vec![];
+        for entry in self.resolved_entries(scope) {
+            let old = entry.scope.get_value(&entry.key)?;
+            if old.as_deref() != Some(entry.value.as_str()) {
+                actions.push(PlannedAction::SetConfig {
+                    scope: entry.scope,
+                    key: entry.key,
+                    old,
+                    new: entry.value,
+                });
+            }
+        }
+        Ok(Plan::from(actions))
+    }
+}
+
+/// Replaces every `${VAR}` with the value of the environment variable `VAR`.
+fn expand_env(value: &str) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| anyhow!("Unterminated '${{' in '{}'", value))?;
+        let name = &rest[start + 2..end];
+        let variable = env::var(name).map_err(|_| anyhow!("{} is not set", name))?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&variable);
+        rest = &rest[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[test]
+fn test_shipped_presets_parse() {
+    for (name, content) in SHIPPED_PRESETS {
+        let preset = ConfigPreset::parse(name, content).unwrap();
+        assert!(!preset.entries.is_empty());
+        assert!(preset
+            .entries
+            .iter()
+            .all(|entry| ConfigKey::parse(&entry.key).is_ok()));
+    }
+}
+
+#[test]
+fn test_preset_entries() {
+    let preset = ConfigPreset::parse(
+        "team",
+        "description = \"Team\"\n[[entries]]\nkey = \"init.defaultBranch\"\nvalue = \"main\"\n[[entries]]\nkey = \"user.signingKey\"\nvalue = \"${HOME}/.ssh/id\"\nscope = \"global\"\n",
+    )
+    .unwrap();
+    let home = env::var("HOME").unwrap();
+
+    let entries = preset.resolved_entries(None);
+    assert_eq!(entries[0].scope, GitConfig::Local);
+    assert_eq!(entries[1].scope, GitConfig::Global);
+    assert_eq!(entries[1].value, format!("{}/.ssh/id", home));
+    assert!(preset
+        .resolved_entries(Some(&GitConfig::Worktree))
+        .iter()
+        .all(|entry| entry.scope == GitConfig::Worktree));
+    assert!(expand_env("${GITSY_SURELY_UNSET_VARIABLE}").is_err());
+}
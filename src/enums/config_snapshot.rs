@@ -0,0 +1,317 @@
+use crate::*;
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Snapshots taken during this run, so that a file is backed up once before its first change.
+static TAKEN: Mutex<Vec<ConfigSnapshot>> = Mutex::new(Vec::new());
+
+#[cfg(test)]
+thread_local! {
+    /// Each test keeps its snapshots in a directory of its own, away from the real state.
+    static TEST_BACKUPS_DIR: tempfile::TempDir = tempfile::tempdir().unwrap();
+}
+
+/// A copy of a git config file taken before gitsy changed it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigSnapshot {
+    #[serde(skip)]
+    pub id: String,
+    #[serde(skip)]
+    dir: PathBuf,
+    pub created: String,
+    pub scope: String,
+    pub path: PathBuf,
+    /// The changes that followed the snapshot, such as `set user.email`.
+    pub changes: Vec<String>,
+    /// Taken by `restore` or `undo` itself; `undo` steps over these.
+    #[serde(default)]
+    pub before_restore: bool,
+    #[serde(default)]
+    pub undone: bool,
+    /// `None` when the file did not exist yet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+impl ConfigSnapshot {
+    /// `config-backups` under the gitsy state directory, which `GITSY_STATE_DIR` overrides.
+    pub fn backups_dir() -> Result<PathBuf> {
+        #[cfg(test)]
+        return Ok(TEST_BACKUPS_DIR.with(|dir| dir.path().to_path_buf()));
+
+        #[cfg(not(test))]
+        Ok(get_state_dir()?.join("config-backups"))
+    }
+
+    /// Copies `path` into a new timestamped snapshot.
+    pub fn take(scope: &str, path: &Path, change: &str, before_restore: bool) -> Result<Self> {
+        Self::take_into(&Self::backups_dir()?, scope, path, change, before_restore)
+    }
+
+    fn take_into(
+        dir: &Path,
+        scope: &str,
+        path: &Path,
+        change: &str,
+        before_restore: bool,
+    ) -> Result<Self> {
+        let content = if path.exists() {
+            Some(
+                read_to_string(path)
+                    .with_context(|| format!("Failed to back up {}", path.display()))?,
+            )
+        } else {
+            None
+        };
+
+        let now = Local::now();
+        let stamp = now.format("%Y%m%d-%H%M%S-%3f").to_string();
+        let mut id = stamp.clone();
+        let mut counter = 1;
+        while dir.join(format!("{}.toml", id)).exists() {
+            id = format!("{}-{}", stamp, counter);
+            counter += 1;
+        }
+
+        let snapshot = Self {
+            id,
+            dir: dir.to_path_buf(),
+            created: now.to_rfc3339(),
+            scope: scope.to_string(),
+            path: path.to_path_buf(),
+            changes: vec![change.to_string()],
+            before_restore,
+            undone: false,
+            content,
+        };
+        snapshot.save()?;
+        Ok(snapshot)
+    }
+
+    /// Every snapshot, oldest first.
+    pub fn list() -> Result<Vec<Self>> {
+        Self::list_in(&Self::backups_dir()?)
+    }
+
+    /// Every snapshot in `dir`, skipping the ones that cannot be read with a warning.
+    fn list_in(dir: &Path) -> Result<Vec<Self>> {
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+
+        let mut snapshots = vec![];
+        for entry in read_dir(dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "toml")
+            {
+                match Self::load_file(&path) {
+                    Ok(snapshot) => snapshots.push(snapshot),
+                    Err(error) => eprintln!("Warning: {}, skipping it", error),
+                }
+            }
+        }
+        snapshots.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(snapshots)
+    }
+
+    pub fn load(id: &str) -> Result<Self> {
+        let path = Self::backups_dir()?.join(format!("{}.toml", id));
+        if !path.exists() {
+            return Err(anyhow!(
+                "No config backup '{}' (see `gitsy config history`)",
+                id
+            ));
+        }
+        Self::load_file(&path)
+    }
+
+    fn load_file(path: &Path) -> Result<Self> {
+        let content =
+            read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut snapshot: Self = toml::from_str(&content)
+            .with_context(|| format!("Invalid config backup {}", path.display()))?;
+        snapshot.id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        snapshot.dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(snapshot)
+    }
+
+    fn save(&self) -> Result<()> {
+        create_dir_all(&self.dir)?;
+        write(
+            self.dir.join(format!("{}.toml", self.id)),
+            toml::to_string(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// The most recent snapshot taken before a change that has not been undone yet, skipping
+    /// the ones whose file matches them already, such as after reverting a change by hand.
+    pub fn last_undoable() -> Result<Option<Self>> {
+        Self::last_undoable_in(&Self::backups_dir()?)
+    }
+
+    fn last_undoable_in(dir: &Path) -> Result<Option<Self>> {
+        Ok(Self::list_in(dir)?.into_iter().rev().find(|snapshot| {
+            !snapshot.before_restore
+                && !snapshot.undone
+                && !matches!(snapshot.plan_restore(), Ok(plan) if plan.is_empty())
+        }))
+    }
+
+    /// The file changes restoring this snapshot would make, as a diff against the current file.
+    pub fn plan_restore(&self) -> Result<Plan> {
+        let current = if self.path.exists() {
+            Some(read_to_string(&self.path)?)
+        } else {
+            None
+        };
+
+        let action = match (current, &self.content) {
+            (Some(old), Some(new)) if old != *new => Some(PlannedAction::ModifyFile {
+                path: self.path.clone(),
+                old,
+                new: new.clone(),
+            }),
+            (None, Some(content)) => Some(PlannedAction::CreateFile {
+                path: self.path.clone(),
+                content: content.clone(),
+            }),
+            (Some(_), None) => Some(PlannedAction::DeleteFile {
+                path: self.path.clone(),
+            }),
+            _ => None,
+        };
+        Ok(Plan::from(action.into_iter().collect::<Vec<_>>()))
+    }
+
+    /// Puts the file back as it was, after snapshotting its current state.
+    pub fn restore(&self) -> Result<()> {
+        let plan = self.plan_restore()?;
+        if plan.is_empty() {
+            return Ok(());
+        }
+
+        Self::take_into(
+            &self.dir,
+            &self.scope,
+            &self.path,
+            &format!("restore {}", self.id),
+            true,
+        )?;
+        plan.apply()
+    }
+
+    pub fn mark_undone(&mut self) -> Result<()> {
+        self.undone = true;
+        self.save()
+    }
+}
+
+impl GitConfig {
+    /// Snapshots this scope's file before its first change in this run,
+    /// and records every later change against that same snapshot.
+    pub(crate) fn backup(&self, change: String) -> Result<()> {
+        let path = self.path()?;
+        let dir = ConfigSnapshot::backups_dir()?;
+        let mut taken = TAKEN.lock().unwrap();
+
+        match taken
+            .iter_mut()
+            .find(|snapshot| snapshot.path == path && snapshot.dir == dir)
+        {
+            Some(snapshot) => {
+                snapshot.changes.push(change);
+                snapshot.save()
+            }
+            None => {
+                taken.push(ConfigSnapshot::take_into(
+                    &dir,
+                    &self.to_string(),
+                    &path,
+                    &change,
+                    false,
+                )?);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[test]
+fn test_snapshot_and_restore() {
+    let temp = tempfile::tempdir().unwrap();
+    let backups = temp.path().join("backups");
+    let config = temp.path().join("config");
+    std::fs::write(&config, "[user]\n\temail = old@example.com\n").unwrap();
+
+    let snapshot =
+        ConfigSnapshot::take_into(&backups, "global", &config, "set user.email", false).unwrap();
+    std::fs::write(&config, "[user]\n\temail = new@example.com\n").unwrap();
+
+    std::fs::write(backups.join("broken.toml"), "created = [").unwrap();
+    let listed = ConfigSnapshot::list_in(&backups).unwrap();
+    assert_eq!(listed, vec![snapshot.clone()]);
+    assert_eq!(
+        ConfigSnapshot::last_undoable_in(&backups).unwrap(),
+        Some(snapshot.clone())
+    );
+    assert!(matches!(
+        snapshot.plan_restore().unwrap().actions.as_slice(),
+        [PlannedAction::ModifyFile { .. }]
+    ));
+
+    snapshot.restore().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&config).unwrap(),
+        "[user]\n\temail = old@example.com\n"
+    );
+    assert!(snapshot.plan_restore().unwrap().is_empty());
+    assert_eq!(ConfigSnapshot::last_undoable_in(&backups).unwrap(), None);
+
+    let listed = ConfigSnapshot::list_in(&backups).unwrap();
+    assert_eq!(listed.len(), 2);
+    assert!(listed[1].before_restore);
+    assert_eq!(
+        listed[1].content.as_deref(),
+        Some("[user]\n\temail = new@example.com\n")
+    );
+
+    //> A change reverted by hand no longer blocks undoing the older ones
+    let older =
+        ConfigSnapshot::take_into(&backups, "global", &config, "set user.email", false).unwrap();
+    std::fs::write(&config, "[user]\n\temail = a@example.com\n").unwrap();
+    ConfigSnapshot::take_into(&backups, "global", &config, "set user.name", false).unwrap();
+    std::fs::write(&config, "[user]\n\temail = a@example.com\n\tname = Jane\n").unwrap();
+    std::fs::write(&config, "[user]\n\temail = a@example.com\n").unwrap();
+    assert_eq!(
+        ConfigSnapshot::last_undoable_in(&backups).unwrap(),
+        Some(older)
+    );
+}
+
+#[test]
+fn test_backup_stays_out_of_the_state_dir() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = temp.path().join("config");
+    std::fs::write(&config, "[user]\n\tname = jane\n").unwrap();
+
+    GitConfig::File(config.clone())
+        .backup("set user.name".to_string())
+        .unwrap();
+    let snapshots = ConfigSnapshot::list().unwrap();
+    assert!(snapshots.iter().any(|snapshot| snapshot.path == config));
+    assert!(!ConfigSnapshot::backups_dir()
+        .unwrap()
+        .starts_with(get_state_dir().unwrap()));
+}
@@ -1,13 +1,22 @@
 use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fmt;
 use std::io::{stdin, stdout, Write};
+use std::path::PathBuf;
 use std::process::{Command, Stdio};
+use std::str::FromStr;
 use crate::*;
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum GitConfig {
     Global,
     Local,
     System,
     Worktree,
+    /// An arbitrary config file, such as a per-profile include file.
+    File(PathBuf),
 }
 
 impl GitConfig {
@@ -17,40 +26,92 @@ impl GitConfig {
             Self::Local => "local",
             Self::System => "system",
             Self::Worktree => "worktree",
+            Self::File(_) => "file",
         }
     }
 
-    pub fn list(&self) -> Result<String> {
-        let title = "Git Config";
-        let scope_option = format!("--{}", self.to_str());
-        let cmd_git_config_list = Command::new("git")
+    /// The `git config` options selecting this scope.
+    fn scope_args(&self) -> Vec<OsString> {
+        match self {
+            Self::File(path) => vec!["--file".into(), path.into()],
+            scope => vec![format!("--{}", scope.to_str()).into()],
+        }
+    }
+
+    pub fn all() -> [Self; 4] {
+        [Self::System, Self::Global, Self::Local, Self::Worktree]
+    }
+
+    /// Every entry of this scope's file, in file order, without following includes.
+    pub fn list(&self) -> Result<Vec<GitConfigEntry>> {
+        if config_backend() == GitConfigBackend::Native {
+            return self.native_list();
+        }
+
+        //> Git fails on a missing file and outside a repository; both are just empty
+        match self.path() {
+            Ok(path) if !path.exists() => return Ok(vec![]),
+            Err(_) if matches!(self, Self::Local | Self::Worktree) => return Ok(vec![]),
+            _ => {}
+        }
+        let output = Command::new("git")
             .arg("config")
-            .arg(&scope_option)
-            .arg("--list")
-            .output()
-            .unwrap_or_else(|_| panic!("Failed to `git config {} --list`", scope_option));
-
-        if cmd_git_config_list.status.success() {
-            Ok(format!(
-                "===| {}: {} |===\n{}",
-                title,
-                self.to_str().to_uppercase(),
-                String::from_utf8_lossy(&cmd_git_config_list.stdout)
-            ))
+            .args(self.scope_args())
+            .args(["--list", "--show-scope", "--show-origin", "--null"])
+            .output()?;
+        if !output.status.success()
+            && String::from_utf8_lossy(&output.stderr)
+                .contains("can only be used inside a git repository")
+        {
+            return Ok(vec![]);
+        }
+
+        Self::parse_list_output(output, Some(self))
+    }
+
+    /// Every entry git sees, across all scopes and included files, in the order it reads them;
+    /// for each key the last entry is the effective one.
+    pub fn list_all() -> Result<Vec<GitConfigEntry>> {
+        if config_backend() == GitConfigBackend::Native {
+            return Self::native_list_all();
+        }
+
+        let output = Command::new("git")
+            .args([
+                "config",
+                "--list",
+                "--show-scope",
+                "--show-origin",
+                "--null",
+            ])
+            .output()?;
+
+        Self::parse_list_output(output, None)
+    }
+
+    fn parse_list_output(
+        output: std::process::Output,
+        scope: Option<&Self>,
+    ) -> Result<Vec<GitConfigEntry>> {
+        if output.status.success() {
+            GitConfigEntry::parse_git_output(&String::from_utf8_lossy(&output.stdout), scope)
         } else {
             Err(anyhow!(
-                "{} Error |> {} |> {}",
-                title,
-                cmd_git_config_list.status,
-                String::from_utf8_lossy(&cmd_git_config_list.stderr),
+                "Git Config Error |> {} |> {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
             ))
         }
     }
 
     pub fn get_value(&self, key: &str) -> Result<Option<String>> {
+        if config_backend() == GitConfigBackend::Native {
+            return self.native_get_value(key);
+        }
+
         let output = Command::new("git")
             .arg("config")
-            .arg(format!("--{}", self.to_str()))
+            .args(self.scope_args())
             .arg("--get")
             .arg(key)
             .output()?;
@@ -79,39 +140,398 @@ impl GitConfig {
     pub fn set_value(&self, key: &str, value: &str) -> Result<()> {
         let current_value = self.get_value(key)?;
 
-        if let Some(current_value) = current_value.as_deref() {
-            if current_value == value {
-                return Ok(());
-            } else if permission_granted(format!(
-                "Update the {} config key '{}' from '{}' to '{}'?",
-                self.to_str(),
-                key,
-                current_value,
-                value
-            )) {
-            } else {
+        if let Some(current) = current_value.as_deref() {
+            if current != value
+                && !is_dry_run()
+                && !permission_granted(format!(
+                    "Update the {} config key '{}' from '{}' to '{}'?",
+                    self, key, current, value
+                ))
+            {
                 return Ok(());
             }
         }
 
+        self.write_value(key, value, current_value)
+    }
+
+    /// Sets `key` like [`Self::set_value`] without asking first, for changes confirmed already.
+    pub fn replace_value(&self, key: &str, value: &str) -> Result<()> {
+        self.write_value(key, value, self.get_value(key)?)
+    }
+
+    fn write_value(&self, key: &str, value: &str, current_value: Option<String>) -> Result<()> {
+        if current_value.as_deref() == Some(value) {
+            return Ok(());
+        }
+
+        if is_dry_run() {
+            println!(
+                "[dry-run] Would update the {} git config: {} = {} (was: {})",
+                self,
+                key,
+                value,
+                current_value.as_deref().unwrap_or("<unset>")
+            );
+            return Ok(());
+        }
+
+        self.backup(format!("set {}", key))?;
+        if config_backend() == GitConfigBackend::Native {
+            self.native_edit(|file| file.set(key, value))?;
+            println!("Updated the {} git config: {} = {}", self, key, value);
+            return Ok(());
+        }
+
+        self.create_parent_dir()?;
         let cmd_update_key = Command::new("git")
             .arg("config")
-            .arg(format!("--{}", self.to_str()))
+            .args(self.scope_args())
             .arg("--replace-all")
             .arg(key)
             .arg(value)
             .output()?;
 
         if cmd_update_key.status.success() {
-            println!(
-                "Updated the {} git config: {} = {}",
-                self.to_str(),
+            println!("Updated the {} git config: {} = {}", self, key, value);
+            Ok(())
+        } else {
+            Err(anyhow!("Failed to update Git config for key: {}", key))
+        }
+    }
+
+    /// Returns every `(key, value)` pair whose key matches the regular expression `pattern`.
+    pub fn get_regexp(&self, pattern: &str) -> Result<Vec<(String, String)>> {
+        if config_backend() == GitConfigBackend::Native {
+            return self.native_get_regexp(pattern);
+        }
+
+        let output = Command::new("git")
+            .arg("config")
+            .args(self.scope_args())
+            .arg("--null")
+            .arg("--get-regexp")
+            .arg(pattern)
+            .output()?;
+
+        //> Exit status 1 only means that nothing matched
+        if !output.status.success() {
+            return match output.status.code() {
+                Some(1) => Ok(vec![]),
+                _ => Err(anyhow!(
+                    "Git Config Error |> {} |> {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr)
+                )),
+            };
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .split_terminator('\0')
+            .map(|entry| match entry.split_once('\n') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => (entry.to_string(), String::new()),
+            })
+            .collect())
+    }
+
+    pub fn unset(&self, key: &str) -> Result<()> {
+        let Some(current_value) = self.get_value(key)? else {
+            return Ok(());
+        };
+        let count = self.get_all(key)?.len();
+        if count > 1 {
+            return Err(anyhow!(
+                "'{}' has {} values in the {} git config; unset all of them instead",
                 key,
-                value
+                count,
+                self
+            ));
+        }
+
+        if is_dry_run() {
+            println!(
+                "[dry-run] Would remove the {} git config: {} (was: {})",
+                self, key, current_value
             );
+            return Ok(());
+        }
+
+        if !permission_granted(format!(
+            "Remove the {} config key '{}' (currently '{}')?",
+            self, key, current_value
+        )) {
+            return Ok(());
+        }
+
+        self.backup(format!("unset {}", key))?;
+        if config_backend() == GitConfigBackend::Native {
+            self.native_edit(|file| file.unset(key))?;
+            println!("Removed the {} git config: {}", self, key);
+            return Ok(());
+        }
+
+        let cmd_unset_key = Command::new("git")
+            .arg("config")
+            .args(self.scope_args())
+            .arg("--unset")
+            .arg(key)
+            .output()?;
+
+        if cmd_unset_key.status.success() {
+            println!("Removed the {} git config: {}", self, key);
             Ok(())
         } else {
-            Err(anyhow!("Failed to update Git config for key: {}", key))
+            Err(anyhow!(
+                "Failed to remove Git config for key: {} |> {}",
+                key,
+                String::from_utf8_lossy(&cmd_unset_key.stderr).trim()
+            ))
+        }
+    }
+
+    /// Returns every value of the multivar `key`, in file order.
+    pub fn get_all(&self, key: &str) -> Result<Vec<String>> {
+        if config_backend() == GitConfigBackend::Native {
+            return self.native_get_all(key);
+        }
+
+        let output = self.run_git(&["--null", "--get-all", key])?;
+
+        //> Exit status 1 only means that the key is not set
+        if !output.status.success() {
+            return match output.status.code() {
+                Some(1) => Ok(vec![]),
+                _ => Err(anyhow!(
+                    "Git Config Error |> {} |> {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr)
+                )),
+            };
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .split_terminator('\0')
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Appends `value` to the multivar `key`, unless it is already one of its values.
+    pub fn add(&self, key: &str, value: &str) -> Result<()> {
+        if self.get_all(key)?.iter().any(|current| current == value) {
+            return Ok(());
+        }
+
+        if is_dry_run() {
+            println!(
+                "[dry-run] Would add to the {} git config: {} = {}",
+                self, key, value
+            );
+            return Ok(());
+        }
+
+        self.backup(format!("add {}", key))?;
+        if config_backend() == GitConfigBackend::Native {
+            self.native_edit(|file| file.add(key, value))?;
+        } else {
+            self.run_git_checked(&["--add", key, value])?;
+        }
+        println!("Added to the {} git config: {} = {}", self, key, value);
+        Ok(())
+    }
+
+    /// Writes `key` without a value, as in `[core] bare`, which git reads as `true`. Git's
+    /// command line cannot write such a key, so this edits the file natively with either backend.
+    pub fn set_bare(&self, key: &str) -> Result<()> {
+        if is_dry_run() {
+            println!(
+                "[dry-run] Would set in the {} git config: {} (no value)",
+                self, key
+            );
+            return Ok(());
+        }
+
+        self.backup(format!("set {}", key))?;
+        self.native_edit(|file| file.set_bare(key))?;
+        println!("Set in the {} git config: {} (no value)", self, key);
+        Ok(())
+    }
+
+    /// Removes every value of the multivar `key`.
+    pub fn unset_all(&self, key: &str) -> Result<()> {
+        let values = self.get_all(key)?;
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        if is_dry_run() {
+            println!(
+                "[dry-run] Would remove the {} git config: {} (was: {})",
+                self,
+                key,
+                values.join(", ")
+            );
+            return Ok(());
+        }
+
+        if !permission_granted(format!(
+            "Remove all {} values of the {} config key '{}' ({})?",
+            values.len(),
+            self,
+            key,
+            values.join(", ")
+        )) {
+            return Ok(());
+        }
+
+        self.backup(format!("unset all {}", key))?;
+        if config_backend() == GitConfigBackend::Native {
+            self.native_edit(|file| file.unset_all(key))?;
+        } else {
+            self.run_git_checked(&["--unset-all", key])?;
+        }
+        println!("Removed the {} git config: {}", self, key);
+        Ok(())
+    }
+
+    /// Renames the section `old` to `new`, such as `remote.origin` to `remote.upstream`.
+    pub fn rename_section(&self, old: &str, new: &str) -> Result<()> {
+        if is_dry_run() {
+            println!(
+                "[dry-run] Would rename the section {} to {} in the {} git config",
+                old, new, self
+            );
+            return Ok(());
+        }
+
+        self.backup(format!("rename section {} to {}", old, new))?;
+        let renamed = if config_backend() == GitConfigBackend::Native {
+            self.native_edit(|file| file.rename_section(old, new))?
+        } else {
+            self.run_git_checked(&["--rename-section", old, new])
+                .map(|_| true)?
+        };
+        if !renamed {
+            return Err(anyhow!("No section '{}' in the {} git config", old, self));
+        }
+
+        println!(
+            "Renamed the section {} to {} in the {} git config",
+            old, new, self
+        );
+        Ok(())
+    }
+
+    /// Removes the section `name` with all of its keys; a missing section is left alone.
+    pub fn remove_section(&self, name: &str) -> Result<()> {
+        let prefix = match name.split_once('.') {
+            Some((section, subsection)) => format!("{}.{}.", section.to_lowercase(), subsection),
+            None => format!("{}.", name.to_lowercase()),
+        };
+        let entries: Vec<GitConfigEntry> = self
+            .list()?
+            .into_iter()
+            .filter(|entry| {
+                entry
+                    .key
+                    .strip_prefix(&prefix)
+                    .is_some_and(|variable| !variable.contains('.'))
+            })
+            .collect();
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        if is_dry_run() {
+            println!(
+                "[dry-run] Would remove the section {} ({} keys) from the {} git config",
+                name,
+                entries.len(),
+                self
+            );
+            return Ok(());
+        }
+
+        if !permission_granted(format!(
+            "Remove the section '{}' and its {} keys from the {} config?",
+            name,
+            entries.len(),
+            self
+        )) {
+            return Ok(());
+        }
+
+        self.backup(format!("remove section {}", name))?;
+        if config_backend() == GitConfigBackend::Native {
+            self.native_edit(|file| file.remove_section(name))?;
+        } else {
+            self.run_git_checked(&["--remove-section", name])?;
+        }
+        println!("Removed the section {} from the {} git config", name, self);
+        Ok(())
+    }
+
+    /// git cannot create a config file in a directory that does not exist yet.
+    fn create_parent_dir(&self) -> Result<()> {
+        match self {
+            Self::File(path) => match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => {
+                    Ok(std::fs::create_dir_all(parent)?)
+                }
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn run_git(&self, args: &[&str]) -> Result<std::process::Output> {
+        Ok(Command::new("git")
+            .arg("config")
+            .args(self.scope_args())
+            .args(args)
+            .output()?)
+    }
+
+    fn run_git_checked(&self, args: &[&str]) -> Result<std::process::Output> {
+        self.create_parent_dir()?;
+        let output = self.run_git(args)?;
+        if output.status.success() {
+            Ok(output)
+        } else {
+            Err(anyhow!(
+                "Git Config Error |> {} |> {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+}
+
+impl fmt::Display for GitConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            scope => write!(f, "{}", scope.to_str()),
+        }
+    }
+}
+
+impl FromStr for GitConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(scope: &str) -> Result<Self> {
+        match scope.to_lowercase().as_str() {
+            "global" => Ok(Self::Global),
+            "local" => Ok(Self::Local),
+            "system" => Ok(Self::System),
+            "worktree" => Ok(Self::Worktree),
+            _ => match scope.strip_prefix("file:") {
+                Some(path) if !path.is_empty() => Ok(Self::File(PathBuf::from(path))),
+                _ => Err(anyhow!(
+                    "Unknown git config scope '{}' (expected system, global, local, worktree or file:<path>)",
+                    scope
+                )),
+            },
         }
     }
 }
@@ -156,10 +576,35 @@ fn test_set_value() {
     GitConfig::Local.set_value("remote.origin.url", "git@github.com:craole-cc/gitsy.git");
 }
 
+#[test]
+fn test_from_str() {
+    assert_eq!("global".parse::<GitConfig>().unwrap(), GitConfig::Global);
+    assert_eq!("Local".parse::<GitConfig>().unwrap(), GitConfig::Local);
+    assert_eq!(
+        "file:/tmp/work.gitconfig".parse::<GitConfig>().unwrap(),
+        GitConfig::File(PathBuf::from("/tmp/work.gitconfig"))
+    );
+    assert!("everywhere".parse::<GitConfig>().is_err());
+}
+
 #[test]
 fn test_list() {
-    printres!(GitConfig::Local.list());
-    printres!(GitConfig::Global.list());
-    printres!(GitConfig::Worktree.list());
-    printres!(GitConfig::System.list());
+    for scope in GitConfig::all() {
+        if let Err(error) = scope.list() {
+            panic!("Listing the {} config failed: {}", scope, error);
+        }
+    }
+}
+
+#[test]
+fn test_list_missing_system_file() {
+    let temp = tempfile::tempdir().unwrap();
+    let previous = std::env::var_os("GIT_CONFIG_SYSTEM");
+    std::env::set_var("GIT_CONFIG_SYSTEM", temp.path().join("missing"));
+    let entries = GitConfig::System.list();
+    match previous {
+        Some(previous) => std::env::set_var("GIT_CONFIG_SYSTEM", previous),
+        None => std::env::remove_var("GIT_CONFIG_SYSTEM"),
+    }
+    assert_eq!(entries.unwrap(), vec![]);
 }
@@ -0,0 +1,194 @@
+use crate::*;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    sync::OnceLock,
+};
+
+/// How [`GitConfig`] reads and writes configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitConfigBackend {
+    /// Spawn `git config` for every call.
+    Git,
+    /// Parse and edit the config files directly, without a git binary.
+    Native,
+}
+
+impl GitConfigBackend {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Git => "git",
+            Self::Native => "native",
+        }
+    }
+
+    /// `Git` when a git binary can be run, `Native` otherwise.
+    pub fn detect() -> Self {
+        static GIT_AVAILABLE: OnceLock<bool> = OnceLock::new();
+        let git_available = *GIT_AVAILABLE.get_or_init(|| {
+            Command::new("git")
+                .arg("--version")
+                .output()
+                .is_ok_and(|output| output.status.success())
+        });
+
+        if git_available {
+            Self::Git
+        } else {
+            Self::Native
+        }
+    }
+}
+
+impl FromStr for GitConfigBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(backend: &str) -> Result<Self> {
+        match backend.to_lowercase().as_str() {
+            "git" => Ok(Self::Git),
+            "native" => Ok(Self::Native),
+            _ => Err(anyhow!(
+                "Unknown git config backend '{}' (expected git or native)",
+                backend
+            )),
+        }
+    }
+}
+
+impl GitConfig {
+    /// The file this scope reads from and writes to, as `git config --<scope>` picks it.
+    pub fn path(&self) -> Result<PathBuf> {
+        match self {
+            Self::System => Ok(env::var_os("GIT_CONFIG_SYSTEM")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("/etc/gitconfig"))),
+            Self::Global => {
+                if let Some(path) = env::var_os("GIT_CONFIG_GLOBAL") {
+                    return Ok(PathBuf::from(path));
+                }
+                let home = dirs::home_dir()
+                    .ok_or_else(|| anyhow!("Unable to locate the home directory"))?
+                    .join(".gitconfig");
+                match xdg_git_config() {
+                    Some(xdg) if !home.exists() && xdg.exists() => Ok(xdg),
+                    _ => Ok(home),
+                }
+            }
+            Self::Local => Ok(common_git_dir(&require_git_dir()?).join("config")),
+            Self::Worktree => Ok(require_git_dir()?.join("config.worktree")),
+            Self::File(path) => Ok(path.clone()),
+        }
+    }
+
+    /// Every file git reads for this scope when it resolves effective values.
+    pub(crate) fn native_files(&self) -> Vec<PathBuf> {
+        match self {
+            Self::System if env::var_os("GIT_CONFIG_NOSYSTEM").is_some() => vec![],
+            Self::Global if env::var_os("GIT_CONFIG_GLOBAL").is_none() => xdg_git_config()
+                .into_iter()
+                .chain(dirs::home_dir().map(|home| home.join(".gitconfig")))
+                .collect(),
+            _ => self.path().into_iter().collect(),
+        }
+    }
+
+    pub(crate) fn native_list(&self) -> Result<Vec<GitConfigEntry>> {
+        let path = match self.path() {
+            Ok(path) => path,
+            //> Outside a repository the local and worktree scopes are simply empty
+            Err(_) if matches!(self, Self::Local | Self::Worktree) => return Ok(vec![]),
+            Err(error) => return Err(error),
+        };
+        Ok(GitConfigFile::load(&path)?
+            .entries()
+            .into_iter()
+            .map(|entry| GitConfigEntry::from_file_entry(entry, self, &path))
+            .collect())
+    }
+
+    pub(crate) fn native_list_all() -> Result<Vec<GitConfigEntry>> {
+        let context = IncludeContext::discover();
+        let mut entries = vec![];
+
+        for scope in Self::all() {
+            for path in scope.native_files() {
+                if !path.exists() {
+                    continue;
+                }
+                let resolved = GitConfigFile::load(&path)?.resolve(&context)?;
+                entries.extend(
+                    resolved
+                        .into_iter()
+                        .map(|entry| GitConfigEntry::from_resolved(entry, &scope)),
+                );
+            }
+        }
+
+        Ok(entries)
+    }
+
+    pub(crate) fn native_get_value(&self, key: &str) -> Result<Option<String>> {
+        let key = ConfigKey::parse(key)?.to_string();
+        Ok(self
+            .native_list()?
+            .into_iter()
+            .rfind(|entry| entry.key == key)
+            .map(|entry| entry.value_str().trim().to_string()))
+    }
+
+    pub(crate) fn native_get_all(&self, key: &str) -> Result<Vec<String>> {
+        let key = ConfigKey::parse(key)?.to_string();
+        Ok(self
+            .native_list()?
+            .into_iter()
+            .filter(|entry| entry.key == key)
+            .map(|entry| entry.value.unwrap_or_default())
+            .collect())
+    }
+
+    pub(crate) fn native_get_regexp(&self, pattern: &str) -> Result<Vec<(String, String)>> {
+        let pattern = Regex::new(pattern)?;
+        Ok(self
+            .native_list()?
+            .into_iter()
+            .filter(|entry| pattern.is_match(&entry.key))
+            .map(|entry| (entry.key, entry.value.unwrap_or_default()))
+            .collect())
+    }
+
+    /// Loads this scope's file, applies `edit` and saves it back.
+    pub(crate) fn native_edit<T>(
+        &self,
+        edit: impl FnOnce(&mut GitConfigFile) -> Result<T>,
+    ) -> Result<T> {
+        let mut file = GitConfigFile::load(self.path()?)?;
+        let result = edit(&mut file)?;
+        file.save()?;
+        Ok(result)
+    }
+}
+
+fn xdg_git_config() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+        .map(|config| config.join("git").join("config"))
+}
+
+fn require_git_dir() -> Result<PathBuf> {
+    discover_git_dir().ok_or_else(|| anyhow!("Not inside a git repository"))
+}
+
+#[test]
+fn test_backend_from_str() {
+    assert_eq!(
+        "Native".parse::<GitConfigBackend>().unwrap(),
+        GitConfigBackend::Native
+    );
+    assert!("libgit2".parse::<GitConfigBackend>().is_err());
+}
@@ -0,0 +1,140 @@
+use crate::*;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    env::current_dir,
+    path::{Path, PathBuf},
+};
+
+/// A single config variable together with where git read it from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GitConfigEntry {
+    pub key: String,
+    /// `None` for a bare `name` line, which git reads as true.
+    pub value: Option<String>,
+    pub scope: GitConfig,
+    /// The file the entry was read from; `None` for blobs, stdin and the command line.
+    pub origin: Option<PathBuf>,
+    pub line: Option<usize>,
+}
+
+impl GitConfigEntry {
+    /// The value as `git config --get` prints it.
+    pub fn value_str(&self) -> &str {
+        self.value.as_deref().unwrap_or_default()
+    }
+
+    /// Parses the output of `git config --list --show-scope --show-origin --null`, tagging
+    /// every entry with `scope` when the listing was of a single scope or file. Otherwise
+    /// entries from the command line (`git -c`) have no scope here and are skipped.
+    pub(crate) fn parse_git_output(output: &str, scope: Option<&GitConfig>) -> Result<Vec<Self>> {
+        let cwd = current_dir()?;
+        let mut fields = output.split_terminator('\0');
+        let mut entries = vec![];
+
+        while let Some(reported) = fields.next() {
+            let (Some(origin), Some(variable)) = (fields.next(), fields.next()) else {
+                return Err(anyhow!("Truncated git config output after '{}'", reported));
+            };
+            let scope = match (scope, reported.parse::<GitConfig>()) {
+                (Some(scope), _) => scope.clone(),
+                (None, Ok(reported)) => reported,
+                (None, Err(_)) => continue,
+            };
+            let (key, value) = match variable.split_once('\n') {
+                Some((key, value)) => (key, Some(value.to_string())),
+                None => (variable, None),
+            };
+
+            entries.push(Self {
+                key: key.to_string(),
+                value,
+                scope,
+                origin: origin.strip_prefix("file:").map(|path| cwd.join(path)),
+                line: None,
+            });
+        }
+
+        Ok(with_lines(entries))
+    }
+
+    pub(crate) fn from_file_entry(
+        entry: ConfigFileEntry,
+        scope: &GitConfig,
+        origin: &Path,
+    ) -> Self {
+        Self {
+            key: entry.key,
+            value: entry.value,
+            scope: scope.clone(),
+            origin: Some(origin.to_path_buf()),
+            line: Some(entry.line),
+        }
+    }
+
+    pub(crate) fn from_resolved(entry: ResolvedEntry, scope: &GitConfig) -> Self {
+        Self {
+            key: entry.key,
+            value: entry.value,
+            scope: scope.clone(),
+            origin: Some(entry.file),
+            line: Some(entry.line),
+        }
+    }
+}
+
+/// git does not report line numbers, so find each entry in its origin file:
+/// the n-th entry of a key from a file is the n-th occurrence of that key in it.
+fn with_lines(mut entries: Vec<GitConfigEntry>) -> Vec<GitConfigEntry> {
+    let mut files: HashMap<PathBuf, Vec<ConfigFileEntry>> = HashMap::new();
+    let mut seen: HashMap<(PathBuf, String), usize> = HashMap::new();
+
+    for entry in &mut entries {
+        let Some(origin) = &entry.origin else {
+            continue;
+        };
+        let file_entries = files.entry(origin.clone()).or_insert_with(|| {
+            GitConfigFile::load(origin)
+                .map(|file| file.entries())
+                .unwrap_or_default()
+        });
+        let occurrence = seen.entry((origin.clone(), entry.key.clone())).or_insert(0);
+
+        entry.line = file_entries
+            .iter()
+            .filter(|file_entry| file_entry.key == entry.key)
+            .nth(*occurrence)
+            .map(|file_entry| file_entry.line);
+        *occurrence += 1;
+    }
+
+    entries
+}
+
+#[test]
+fn test_parse_git_output() {
+    let temp = tempfile::tempdir().unwrap();
+    let config = temp.path().join("config");
+    std::fs::write(
+        &config,
+        "[core]\n\tbare = false\n[remote \"origin\"]\n\tfetch = a\n\tfetch = b\n[x]\n\tflag\n",
+    )
+    .unwrap();
+
+    let origin = format!("file:{}", config.display());
+    let output = format!(
+        "local\0{0}\0core.bare\nfalse\0local\0{0}\0remote.origin.fetch\na\0local\0{0}\0remote.origin.fetch\nb\0local\0{0}\0x.flag\0command\0command line:\0user.name\nJane\0",
+        origin
+    );
+    let entries = GitConfigEntry::parse_git_output(&output, None).unwrap();
+
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[2].value.as_deref(), Some("b"));
+    assert_eq!(entries[2].line, Some(5));
+    assert_eq!(entries[3].value, None);
+    assert_eq!(entries[3].line, Some(7));
+    assert!(entries
+        .iter()
+        .all(|entry| entry.scope == GitConfig::Local && entry.origin.as_ref() == Some(&config)));
+}
@@ -0,0 +1,1054 @@
+use crate::*;
+use anyhow::{anyhow, Context, Result};
+use std::{
+    fmt,
+    fs::{create_dir_all, read_to_string, write},
+    path::{Path, PathBuf},
+};
+
+/// How deep `include.path` chains are followed before giving up, as git does.
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ConfigItem {
+    /// A `[section]` or `[section "subsection"]` header, plus any trailing comment.
+    Section {
+        raw: String,
+        section: String,
+        subsection: Option<String>,
+    },
+    /// A variable, possibly spanning several lines through `\` continuations.
+    /// `inline` entries follow their section header on the same line.
+    Entry {
+        raw: String,
+        section: String,
+        subsection: Option<String>,
+        name: String,
+        value: Option<String>,
+        inline: bool,
+    },
+    /// Blank lines and comments.
+    Other { raw: String },
+}
+
+impl ConfigItem {
+    fn raw(&self) -> &str {
+        match self {
+            Self::Section { raw, .. } | Self::Entry { raw, .. } | Self::Other { raw } => raw,
+        }
+    }
+
+    fn is_inline(&self) -> bool {
+        matches!(self, Self::Entry { inline: true, .. })
+    }
+
+    fn is_in(&self, key: &ConfigKey) -> bool {
+        match self {
+            Self::Section {
+                section,
+                subsection,
+                ..
+            }
+            | Self::Entry {
+                section,
+                subsection,
+                ..
+            } => *section == key.section && *subsection == key.subsection,
+            Self::Other { .. } => false,
+        }
+    }
+
+    fn matches(&self, key: &ConfigKey) -> bool {
+        match self {
+            Self::Entry { name, .. } => self.is_in(key) && name.eq_ignore_ascii_case(&key.name),
+            _ => false,
+        }
+    }
+}
+
+/// A `section[.subsection].name` key, with section and name lowercased as git compares them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigKey {
+    pub section: String,
+    pub subsection: Option<String>,
+    pub name: String,
+}
+
+impl ConfigKey {
+    pub fn parse(key: &str) -> Result<Self> {
+        let (section, rest) = key
+            .split_once('.')
+            .ok_or_else(|| anyhow!("Config key has no section: '{}'", key))?;
+        let (subsection, name) = match rest.rsplit_once('.') {
+            Some((subsection, name)) => (Some(subsection.to_string()), name),
+            None => (None, rest),
+        };
+        if section.is_empty() || name.is_empty() {
+            return Err(anyhow!("Invalid config key: '{}'", key));
+        }
+
+        Ok(Self {
+            section: section.to_lowercase(),
+            subsection,
+            name: name.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for ConfigKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.subsection {
+            Some(subsection) => write!(
+                f,
+                "{}.{}.{}",
+                self.section,
+                subsection,
+                self.name.to_lowercase()
+            ),
+            None => write!(f, "{}.{}", self.section, self.name.to_lowercase()),
+        }
+    }
+}
+
+/// A variable as read from a file: `value` is `None` for a bare `name` line, which git reads as true.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFileEntry {
+    pub key: String,
+    pub value: Option<String>,
+    pub line: usize,
+}
+
+/// A variable read through [`GitConfigFile::resolve`], with the file it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedEntry {
+    pub key: String,
+    pub value: Option<String>,
+    pub file: PathBuf,
+    pub line: usize,
+    /// The include chain that led to `file`, outermost first.
+    pub via: Vec<PathBuf>,
+}
+
+/// What `includeIf` conditions are evaluated against.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IncludeContext {
+    pub git_dir: Option<PathBuf>,
+    pub branch: Option<String>,
+}
+
+impl IncludeContext {
+    /// The repository around the current directory, found without running git.
+    pub fn discover() -> Self {
+        let git_dir = discover_git_dir();
+        let branch = git_dir.as_deref().and_then(read_head_branch);
+        Self { git_dir, branch }
+    }
+
+    /// Whether the condition of `[includeIf "<condition>"]` holds, relative to `file`.
+    pub fn matches(&self, condition: &str, file: &Path) -> bool {
+        let (kind, pattern) = match condition.split_once(':') {
+            Some(parts) => parts,
+            None => return false,
+        };
+
+        match kind {
+            "gitdir" | "gitdir/i" => {
+                let Some(git_dir) = &self.git_dir else {
+                    return false;
+                };
+                let pattern = gitdir_pattern(pattern, file);
+                let git_dir = git_dir.display().to_string();
+                wildmatch(&pattern, &git_dir, kind == "gitdir/i")
+            }
+            "onbranch" => {
+                let Some(branch) = &self.branch else {
+                    return false;
+                };
+                let mut pattern = pattern.to_string();
+                if pattern.ends_with('/') {
+                    pattern.push_str("**");
+                }
+                wildmatch(&pattern, branch, false)
+            }
+            //> `hasconfig:` needs the fully read config and is left to git
+            _ => false,
+        }
+    }
+}
+
+/// A git config file that keeps comments, spacing and order when it is rewritten.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitConfigFile {
+    pub path: Option<PathBuf>,
+    items: Vec<ConfigItem>,
+    trailing_newline: bool,
+}
+
+impl GitConfigFile {
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut items = vec![];
+        let mut section: Option<(String, Option<String>)> = None;
+        let mut pos = 0;
+
+        while pos < content.len() {
+            let line_end = content[pos..].find('\n').map_or(content.len(), |i| pos + i);
+            let line = &content[pos..line_end];
+            let trimmed = line.trim_start();
+            let line_number = content[..pos].matches('\n').count() + 1;
+
+            if trimmed.trim_end().is_empty() || trimmed.starts_with(['#', ';']) {
+                items.push(ConfigItem::Other {
+                    raw: line.to_string(),
+                });
+                pos = line_end + 1;
+                continue;
+            }
+
+            let mut entry_start = pos;
+            let mut inline = false;
+            if trimmed.starts_with('[') {
+                let indent = line.len() - trimmed.len();
+                let (name, subsection, header_len) = parse_header(trimmed)
+                    .with_context(|| format!("Invalid section header on line {}", line_number))?;
+                let header_end = pos + indent + header_len;
+                let rest = content[header_end..line_end].trim_start();
+
+                //> Keep a trailing comment as part of the header line
+                let raw_end = if rest.trim_end().is_empty() || rest.starts_with(['#', ';']) {
+                    line_end
+                } else {
+                    header_end
+                };
+                items.push(ConfigItem::Section {
+                    raw: content[pos..raw_end].to_string(),
+                    section: name.clone(),
+                    subsection: subsection.clone(),
+                });
+                section = Some((name, subsection));
+
+                if raw_end == line_end {
+                    pos = line_end + 1;
+                    continue;
+                }
+                entry_start = header_end;
+                inline = true;
+            }
+
+            let (section_name, subsection) = section
+                .clone()
+                .ok_or_else(|| anyhow!("Variable outside of a section on line {}", line_number))?;
+            let (name, value, end) = parse_entry(content, entry_start)
+                .with_context(|| format!("Invalid variable on line {}", line_number))?;
+            items.push(ConfigItem::Entry {
+                raw: content[entry_start..end].to_string(),
+                section: section_name,
+                subsection,
+                name,
+                value,
+                inline,
+            });
+            pos = end + 1;
+        }
+
+        Ok(Self {
+            path: None,
+            items,
+            trailing_newline: content.ends_with('\n'),
+        })
+    }
+
+    /// Reads the file at `path`, starting empty if it does not exist yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = if path.exists() {
+            let content = read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            Self::parse(&content).with_context(|| format!("Failed to parse {}", path.display()))?
+        } else {
+            Self::default()
+        };
+        file.path = Some(path.to_path_buf());
+        Ok(file)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("This config has no file to be saved to"))?;
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                create_dir_all(parent)?;
+            }
+        }
+        write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Every variable of this file alone, in order, without following includes.
+    pub fn entries(&self) -> Vec<ConfigFileEntry> {
+        let mut line = 1;
+        let mut entries = vec![];
+
+        for item in &self.items {
+            if let ConfigItem::Entry {
+                section,
+                subsection,
+                name,
+                value,
+                ..
+            } = item
+            {
+                let key = ConfigKey {
+                    section: section.clone(),
+                    subsection: subsection.clone(),
+                    name: name.clone(),
+                };
+                entries.push(ConfigFileEntry {
+                    key: key.to_string(),
+                    value: value.clone(),
+                    line: if item.is_inline() { line - 1 } else { line },
+                });
+            }
+            if !item.is_inline() {
+                line += 1;
+            }
+            line += item.raw().matches('\n').count();
+        }
+
+        entries
+    }
+
+    /// The last value of `key`, which is the one git uses.
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.get_all(key)?.pop())
+    }
+
+    pub fn get_all(&self, key: &str) -> Result<Vec<String>> {
+        let key = ConfigKey::parse(key)?;
+        Ok(self
+            .items
+            .iter()
+            .filter(|item| item.matches(&key))
+            .filter_map(|item| match item {
+                ConfigItem::Entry { value, .. } => Some(value.clone().unwrap_or_default()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Replaces every value of `key` with a single `value`, in place of the last one.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        self.set_entry(key, Some(value))
+    }
+
+    /// Like [`Self::set`], writing `key` without a value, which git reads as `true`.
+    pub fn set_bare(&mut self, key: &str) -> Result<()> {
+        self.set_entry(key, None)
+    }
+
+    fn set_entry(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        let config_key = ConfigKey::parse(key)?;
+        let positions = self.positions(&config_key);
+
+        match positions.split_last() {
+            Some((&last, rest)) => {
+                let inline = self.items[last].is_inline();
+                self.items[last] = new_entry(&config_key, value, inline);
+                rest.iter().rev().for_each(|&index| self.remove_item(index));
+                Ok(())
+            }
+            None => self.add_entry(key, value),
+        }
+    }
+
+    /// Appends another value for `key` after the last variable of its section.
+    pub fn add(&mut self, key: &str, value: &str) -> Result<()> {
+        self.add_entry(key, Some(value))
+    }
+
+    fn add_entry(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        let config_key = ConfigKey::parse(key)?;
+        let entry = new_entry(&config_key, value, false);
+
+        let section_start = self.items.iter().rposition(|item| {
+            matches!(item, ConfigItem::Section { .. }) && item.is_in(&config_key)
+        });
+        match section_start {
+            Some(start) => {
+                let position = self.items[start..]
+                    .iter()
+                    .enumerate()
+                    .skip(1)
+                    .take_while(|(_, item)| !matches!(item, ConfigItem::Section { .. }))
+                    .filter(|(_, item)| matches!(item, ConfigItem::Entry { .. }))
+                    .last()
+                    .map_or(start + 1, |(offset, _)| start + offset + 1);
+                self.items.insert(position, entry);
+            }
+            None => {
+                if self.items.is_empty() {
+                    self.trailing_newline = true;
+                }
+                self.items.push(new_section(&config_key));
+                self.items.push(entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the single value of `key`; fails if there are several. Returns whether it was set.
+    pub fn unset(&mut self, key: &str) -> Result<bool> {
+        let positions = self.positions(&ConfigKey::parse(key)?);
+        match positions.as_slice() {
+            [] => Ok(false),
+            [index] => {
+                self.remove_item(*index);
+                Ok(true)
+            }
+            _ => Err(anyhow!(
+                "'{}' has {} values; unset all of them instead",
+                key,
+                positions.len()
+            )),
+        }
+    }
+
+    /// Removes every value of `key`. Returns how many were removed.
+    pub fn unset_all(&mut self, key: &str) -> Result<usize> {
+        let positions = self.positions(&ConfigKey::parse(key)?);
+        positions
+            .iter()
+            .rev()
+            .for_each(|&index| self.remove_item(index));
+        Ok(positions.len())
+    }
+
+    /// Every variable of this file and of the files it includes, in the order git reads them.
+    pub fn resolve(&self, context: &IncludeContext) -> Result<Vec<ResolvedEntry>> {
+        let mut entries = vec![];
+        self.resolve_into(context, &mut vec![], &mut entries)?;
+        Ok(entries)
+    }
+
+    fn resolve_into(
+        &self,
+        context: &IncludeContext,
+        via: &mut Vec<PathBuf>,
+        entries: &mut Vec<ResolvedEntry>,
+    ) -> Result<()> {
+        let file = self.path.clone().unwrap_or_default();
+
+        for entry in self.entries() {
+            let include = match include_condition(&entry.key) {
+                Some(None) => true,
+                Some(Some(condition)) => context.matches(condition, &file),
+                None => false,
+            };
+            let include_path = entry
+                .value
+                .as_deref()
+                .filter(|_| include)
+                .map(|value| include_path(value, &file));
+
+            entries.push(ResolvedEntry {
+                key: entry.key,
+                value: entry.value,
+                file: file.clone(),
+                line: entry.line,
+                via: via.clone(),
+            });
+
+            //> Missing include files are silently skipped, as git does
+            if let Some(path) = include_path.filter(|path| path.exists()) {
+                if via.len() >= MAX_INCLUDE_DEPTH {
+                    return Err(anyhow!(
+                        "Exceeded the maximum include depth at {}",
+                        path.display()
+                    ));
+                }
+                via.push(file.clone());
+                Self::load(&path)?.resolve_into(context, via, entries)?;
+                via.pop();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renames every `old` section header, such as `remote.origin`, to `new`. Returns whether any existed.
+    pub fn rename_section(&mut self, old: &str, new: &str) -> Result<bool> {
+        let old = parse_section_name(old)?;
+        let new = parse_section_name(new)?;
+        let mut renamed = false;
+
+        for item in &mut self.items {
+            if !item.is_in(&old) {
+                continue;
+            }
+            match item {
+                ConfigItem::Section { .. } => {
+                    *item = new_section(&new);
+                    renamed = true;
+                }
+                ConfigItem::Entry {
+                    section,
+                    subsection,
+                    ..
+                } => {
+                    *section = new.section.clone();
+                    *subsection = new.subsection.clone();
+                }
+                ConfigItem::Other { .. } => {}
+            }
+        }
+
+        Ok(renamed)
+    }
+
+    /// Removes every `name` section with its variables and comments. Returns whether any existed.
+    pub fn remove_section(&mut self, name: &str) -> Result<bool> {
+        let key = parse_section_name(name)?;
+        let mut removed = false;
+
+        while let Some(start) = self
+            .items
+            .iter()
+            .position(|item| matches!(item, ConfigItem::Section { .. }) && item.is_in(&key))
+        {
+            let end = self.items[start + 1..]
+                .iter()
+                .position(|item| matches!(item, ConfigItem::Section { .. }))
+                .map_or(self.items.len(), |offset| start + 1 + offset);
+            self.items.drain(start..end);
+            removed = true;
+        }
+
+        Ok(removed)
+    }
+
+    fn positions(&self, key: &ConfigKey) -> Vec<usize> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.matches(key))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Removes an item, and its section header too once the section holds nothing but blank lines.
+    fn remove_item(&mut self, index: usize) {
+        self.items.remove(index);
+
+        let Some(start) = self.items[..index]
+            .iter()
+            .rposition(|item| matches!(item, ConfigItem::Section { .. }))
+        else {
+            return;
+        };
+        let end = self.items[start + 1..]
+            .iter()
+            .position(|item| matches!(item, ConfigItem::Section { .. }))
+            .map_or(self.items.len(), |offset| start + 1 + offset);
+        let empty = self.items[start + 1..end]
+            .iter()
+            .all(|item| item.raw().trim().is_empty());
+
+        if empty {
+            self.items.drain(start..end);
+        }
+    }
+}
+
+impl fmt::Display for GitConfigFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, item) in self.items.iter().enumerate() {
+            if index > 0 && !item.is_inline() {
+                writeln!(f)?;
+            }
+            write!(f, "{}", item.raw())?;
+        }
+        if self.trailing_newline && !self.items.is_empty() {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// `Some(None)` for `include.path`, `Some(Some(condition))` for `includeIf.<condition>.path`.
+pub(crate) fn include_condition(key: &str) -> Option<Option<&str>> {
+    if key == "include.path" {
+        return Some(None);
+    }
+    key.strip_prefix("includeif.")
+        .and_then(|rest| rest.strip_suffix(".path"))
+        .map(Some)
+}
+
+/// Include paths are relative to the including file, and may start with `~/`.
+pub(crate) fn include_path(value: &str, file: &Path) -> PathBuf {
+    let path = expand_home(value);
+    if path.is_absolute() {
+        path
+    } else {
+        file.parent().unwrap_or(Path::new("")).join(path)
+    }
+}
+
+fn expand_home(value: &str) -> PathBuf {
+    match (value.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(value),
+    }
+}
+
+/// Turns a `gitdir:` pattern into the wildmatch pattern git uses for it.
+fn gitdir_pattern(pattern: &str, file: &Path) -> String {
+    let mut pattern = if let Some(rest) = pattern.strip_prefix("./") {
+        file.parent()
+            .unwrap_or(Path::new(""))
+            .join(rest)
+            .display()
+            .to_string()
+    } else if pattern.starts_with("~/") {
+        expand_home(pattern).display().to_string()
+    } else if pattern.starts_with('/') {
+        pattern.to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+
+    if pattern.ends_with('/') {
+        pattern.push_str("**");
+    }
+    pattern
+}
+
+/// Finds the git directory around the current directory, honouring `GIT_DIR`.
+pub fn discover_git_dir() -> Option<PathBuf> {
+    if let Some(git_dir) = std::env::var_os("GIT_DIR") {
+        return Some(PathBuf::from(git_dir));
+    }
+
+    find_git_dir(&std::env::current_dir().ok()?)
+}
+
+/// Finds the git directory of the repository containing `start`, or `start` itself for a bare repository.
+pub fn find_git_dir(start: &Path) -> Option<PathBuf> {
+    start.ancestors().find_map(|dir| {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            Some(dot_git)
+        } else if dot_git.is_file() {
+            //> Worktrees and submodules use a `gitdir: <path>` file
+            let content = read_to_string(&dot_git).ok()?;
+            let git_dir = content.trim().strip_prefix("gitdir:")?.trim();
+            Some(dir.join(git_dir))
+        } else if dir.join("HEAD").is_file() && dir.join("config").is_file() {
+            Some(dir.to_path_buf())
+        } else {
+            None
+        }
+    })
+}
+
+/// The directory shared by every worktree of the repository, holding the local `config`.
+pub fn common_git_dir(git_dir: &Path) -> PathBuf {
+    match read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => git_dir.join(common_dir.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+fn read_head_branch(git_dir: &Path) -> Option<String> {
+    let head = read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(str::to_string)
+}
+
+/// Parses `[section]`, `[section "subsection"]` or the legacy `[section.subsection]`,
+/// returning the lowercased section, the subsection and the length up to `]`.
+fn parse_header(header: &str) -> Result<(String, Option<String>, usize)> {
+    let body = &header[1..];
+    let name_len = body
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '.'))
+        .ok_or_else(|| anyhow!("Unterminated section header"))?;
+    let name = &body[..name_len];
+    let rest = &body[name_len..];
+
+    if let Some(after) = rest.strip_prefix(']') {
+        let header_len = header.len() - after.len();
+        return match name.split_once('.') {
+            Some((section, subsection)) => Ok((
+                section.to_lowercase(),
+                Some(subsection.to_lowercase()),
+                header_len,
+            )),
+            None if !name.is_empty() => Ok((name.to_lowercase(), None, header_len)),
+            None => Err(anyhow!("Empty section name")),
+        };
+    }
+
+    let quoted = rest
+        .trim_start_matches([' ', '\t'])
+        .strip_prefix('"')
+        .ok_or_else(|| anyhow!("Expected ']' or a quoted subsection"))?;
+    let mut subsection = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => {
+                let (_, escaped) = chars
+                    .next()
+                    .ok_or_else(|| anyhow!("Unterminated subsection"))?;
+                subsection.push(escaped);
+            }
+            '"' => {
+                let after = quoted[index + 1..]
+                    .strip_prefix(']')
+                    .ok_or_else(|| anyhow!("Expected ']' after the subsection"))?;
+                return Ok((
+                    name.to_lowercase(),
+                    Some(subsection),
+                    header.len() - after.len(),
+                ));
+            }
+            '\n' => break,
+            c => subsection.push(c),
+        }
+    }
+    Err(anyhow!("Unterminated subsection"))
+}
+
+/// Parses `name [= value]` starting at `start`, returning the name, the value and
+/// the index of the newline (or end of input) that ends the entry.
+fn parse_entry(content: &str, start: usize) -> Result<(String, Option<String>, usize)> {
+    let rest = &content[start..];
+    let name_start = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    let name_len = rest[name_start..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(rest.len() - name_start);
+    let name = &rest[name_start..name_start + name_len];
+    if name.is_empty() || !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(anyhow!("Invalid variable name"));
+    }
+
+    let after_name = start + name_start + name_len;
+    let after_space = after_name + content[after_name..].len()
+        - content[after_name..]
+            .trim_start_matches([' ', '\t', '\r'])
+            .len();
+
+    match content[after_space..].chars().next() {
+        Some('=') => {
+            let (value, end) = parse_value(content, after_space + 1)?;
+            Ok((name.to_string(), Some(value), end))
+        }
+        None | Some('\n') | Some('#') | Some(';') => {
+            let end = content[after_space..]
+                .find('\n')
+                .map_or(content.len(), |i| after_space + i);
+            Ok((name.to_string(), None, end))
+        }
+        Some(c) => Err(anyhow!("Unexpected '{}' after the variable name", c)),
+    }
+}
+
+/// Decodes a value as git does: quotes are dropped, escapes resolved, comments and the
+/// surrounding unquoted whitespace stripped, `\` line continuations followed, and each
+/// unquoted whitespace character inside the value read as a space.
+fn parse_value(content: &str, start: usize) -> Result<(String, usize)> {
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut spaces = 0;
+    let mut chars = content[start..].char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\n' if !quoted => return Ok((value, start + offset)),
+            '\n' => return Err(anyhow!("Unterminated quote")),
+            c if c.is_whitespace() && !quoted => {
+                if !value.is_empty() {
+                    spaces += 1;
+                }
+                continue;
+            }
+            '#' | ';' if !quoted => {
+                let end = content[start + offset..]
+                    .find('\n')
+                    .map_or(content.len(), |i| start + offset + i);
+                return Ok((value, end));
+            }
+            _ => {}
+        }
+
+        //> Whitespace inside the value counts once it is followed by more of it
+        value.extend(std::iter::repeat_n(' ', spaces));
+        spaces = 0;
+
+        match c {
+            '"' => quoted = !quoted,
+            '\\' => {
+                let (_, escaped) = chars.next().ok_or_else(|| anyhow!("Trailing backslash"))?;
+                match escaped {
+                    '\n' => {}
+                    '\r' if chars.peek().is_some_and(|(_, c)| *c == '\n') => {
+                        chars.next();
+                    }
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'b' => value.push('\u{8}'),
+                    '\\' | '"' => value.push(escaped),
+                    other => return Err(anyhow!("Invalid escape '\\{}'", other)),
+                }
+            }
+            c => value.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(anyhow!("Unterminated quote"));
+    }
+    Ok((value, content.len()))
+}
+
+/// Renders a value so that [`parse_value`] reads it back unchanged.
+fn quote_value(value: &str) -> String {
+    let needs_quotes = value.starts_with(char::is_whitespace)
+        || value.ends_with(char::is_whitespace)
+        || value.contains(['#', ';']);
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\u{8}', "\\b");
+
+    if needs_quotes {
+        format!("\"{}\"", escaped)
+    } else {
+        escaped
+    }
+}
+
+fn new_entry(key: &ConfigKey, value: Option<&str>, inline: bool) -> ConfigItem {
+    let indent = if inline { " " } else { "\t" };
+    let raw = match value {
+        Some(value) => format!("{}{} = {}", indent, key.name, quote_value(value)),
+        None => format!("{}{}", indent, key.name),
+    };
+    ConfigItem::Entry {
+        raw,
+        section: key.section.clone(),
+        subsection: key.subsection.clone(),
+        name: key.name.clone(),
+        value: value.map(str::to_string),
+        inline,
+    }
+}
+
+/// Parses a section name such as `core` or `remote.origin` into a key without a variable name.
+fn parse_section_name(name: &str) -> Result<ConfigKey> {
+    let (section, subsection) = match name.split_once('.') {
+        Some((section, subsection)) => (section, Some(subsection.to_string())),
+        None => (name, None),
+    };
+    if section.is_empty() {
+        return Err(anyhow!("Invalid section name: '{}'", name));
+    }
+
+    Ok(ConfigKey {
+        section: section.to_lowercase(),
+        subsection,
+        name: String::new(),
+    })
+}
+
+fn new_section(key: &ConfigKey) -> ConfigItem {
+    let raw = match &key.subsection {
+        Some(subsection) => format!(
+            "[{} \"{}\"]",
+            key.section,
+            subsection.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+        None => format!("[{}]", key.section),
+    };
+    ConfigItem::Section {
+        raw,
+        section: key.section.clone(),
+        subsection: key.subsection.clone(),
+    }
+}
+
+#[test]
+fn test_parse_sections_quoting_and_escapes() {
+    let content = "# top\n[core]\n\tbare\n\teditor = \"code --wait\" ; comment\n[remote \"Origin\"]\n\turl = git@host:a/b.git\n[Sec.Sub] k = v\n[alias]\n\tlg = log \\\n\t\t--oneline\n\tq = \"a\\tb\\\\\\\"c\"\n";
+    let file = GitConfigFile::parse(content).unwrap();
+
+    assert_eq!(
+        file.entries(),
+        vec![
+            ConfigFileEntry {
+                key: "core.bare".to_string(),
+                value: None,
+                line: 3
+            },
+            ConfigFileEntry {
+                key: "core.editor".to_string(),
+                value: Some("code --wait".to_string()),
+                line: 4
+            },
+            ConfigFileEntry {
+                key: "remote.Origin.url".to_string(),
+                value: Some("git@host:a/b.git".to_string()),
+                line: 6
+            },
+            ConfigFileEntry {
+                key: "sec.sub.k".to_string(),
+                value: Some("v".to_string()),
+                line: 7
+            },
+            ConfigFileEntry {
+                key: "alias.lg".to_string(),
+                value: Some("log   --oneline".to_string()),
+                line: 9
+            },
+            ConfigFileEntry {
+                key: "alias.q".to_string(),
+                value: Some("a\tb\\\"c".to_string()),
+                line: 11
+            },
+        ]
+    );
+    assert_eq!(file.to_string(), content);
+}
+
+#[test]
+fn test_multivars_and_edits_keep_formatting() {
+    let content = "[core]\n    # keep me\n    autocrlf = true\n[remote \"origin\"]\n\tfetch = a\n\tfetch = b\n";
+    let mut file = GitConfigFile::parse(content).unwrap();
+
+    assert_eq!(file.get_all("remote.origin.fetch").unwrap(), ["a", "b"]);
+    assert!(file.unset("remote.origin.fetch").is_err());
+
+    file.set("core.autocrlf", "false").unwrap();
+    file.add("remote.origin.fetch", "c").unwrap();
+    file.set("user.name", " Jane # Doe").unwrap();
+    assert_eq!(
+        file.to_string(),
+        "[core]\n    # keep me\n\tautocrlf = false\n[remote \"origin\"]\n\tfetch = a\n\tfetch = b\n\tfetch = c\n[user]\n\tname = \" Jane # Doe\"\n"
+    );
+    assert_eq!(
+        GitConfigFile::parse(&file.to_string())
+            .unwrap()
+            .get("user.name")
+            .unwrap()
+            .as_deref(),
+        Some(" Jane # Doe")
+    );
+
+    assert_eq!(file.unset_all("remote.origin.fetch").unwrap(), 3);
+    assert!(file.unset("user.name").unwrap());
+    assert_eq!(
+        file.to_string(),
+        "[core]\n    # keep me\n\tautocrlf = false\n"
+    );
+    file.set_bare("core.autocrlf").unwrap();
+    file.set_bare("core.bare").unwrap();
+    assert_eq!(
+        file.to_string(),
+        "[core]\n    # keep me\n\tautocrlf\n\tbare\n"
+    );
+    assert_eq!(file.entries().last().unwrap().value, None);
+}
+
+#[test]
+fn test_rename_and_remove_sections() {
+    let content = "[remote \"origin\"]\n\turl = a\n[core]\n\tbare = false\n# about origin\n[remote \"origin\"]\n\tfetch = b\n";
+    let mut file = GitConfigFile::parse(content).unwrap();
+
+    assert!(file
+        .rename_section("remote.origin", "remote.upstream")
+        .unwrap());
+    assert_eq!(
+        file.get("remote.upstream.url").unwrap().as_deref(),
+        Some("a")
+    );
+    assert_eq!(file.get("remote.origin.url").unwrap(), None);
+    assert!(!file.rename_section("remote.origin", "remote.x").unwrap());
+
+    assert!(file.remove_section("remote.upstream").unwrap());
+    assert_eq!(file.to_string(), "[core]\n\tbare = false\n# about origin\n");
+    assert!(!file.remove_section("remote.upstream").unwrap());
+}
+
+#[test]
+fn test_resolve_includes() {
+    let temp = tempfile::tempdir().unwrap();
+    let work = temp.path().join("work.gitconfig");
+    let shared = temp.path().join("shared.gitconfig");
+    let main = temp.path().join("config");
+    std::fs::write(&work, "[user]\n\temail = jane@work.example\n").unwrap();
+    std::fs::write(&shared, "[core]\n\tautocrlf = false\n").unwrap();
+    std::fs::write(
+        &main,
+        "[user]\n\temail = jane@home.example\n[include]\n\tpath = shared.gitconfig\n[includeIf \"gitdir:/srv/work/\"]\n\tpath = work.gitconfig\n",
+    )
+    .unwrap();
+
+    let file = GitConfigFile::load(&main).unwrap();
+    let outside = IncludeContext {
+        git_dir: Some(PathBuf::from("/srv/oss/app/.git")),
+        branch: None,
+    };
+    let inside = IncludeContext {
+        git_dir: Some(PathBuf::from("/srv/work/app/.git")),
+        branch: None,
+    };
+
+    let emails = |context: &IncludeContext| -> Vec<(Option<String>, Vec<PathBuf>)> {
+        file.resolve(context)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.key == "user.email")
+            .map(|entry| (entry.value, entry.via))
+            .collect()
+    };
+    assert_eq!(emails(&outside).len(), 1);
+    assert_eq!(
+        emails(&inside).last().unwrap(),
+        &(Some("jane@work.example".to_string()), vec![main.clone()])
+    );
+    assert!(file
+        .resolve(&outside)
+        .unwrap()
+        .iter()
+        .any(|entry| entry.key == "core.autocrlf" && entry.file == shared));
+}
+
+#[test]
+fn test_values_match_git() {
+    let content = "[alias]\n\tlg = log \\\n\t\t--oneline\n\tbs = a\\bc\n\truns = x  \t y # comment\n\tmixed = \"a  b\"  c \n\tesc = \"q\\\"\\\\\\tz\"\n\tlead =   \t padded\t ; trailing\n\tcont = one\\\n  two\n";
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("config");
+    write(&path, content).unwrap();
+
+    let entries = GitConfigFile::parse(content).unwrap().entries();
+    assert_eq!(entries.len(), 7);
+    for entry in entries {
+        let output = std::process::Command::new("git")
+            .args(["config", "--file"])
+            .arg(&path)
+            .args(["--get", &entry.key])
+            .output()
+            .unwrap();
+        let expected = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(
+            entry.value.as_deref(),
+            expected.strip_suffix('\n'),
+            "{}",
+            entry.key
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use crate::*;
+use anyhow::Result;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The steps of the daily sync, in the order `gitup_from_bin` in `scripts/gitup` runs them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitUpStep {
+    Pull,
+    Reset,
+    Status,
+    AddAll,
+    Commit,
+    Push,
+}
+
+impl GitUpStep {
+    pub fn all() -> [Self; 6] {
+        [
+            Self::Pull,
+            Self::Reset,
+            Self::Status,
+            Self::AddAll,
+            Self::Commit,
+            Self::Push,
+        ]
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Pull => "pull",
+            Self::Reset => "reset",
+            Self::Status => "status",
+            Self::AddAll => "add",
+            Self::Commit => "commit",
+            Self::Push => "push",
+        }
+    }
+
+    /// Process exit status reported when this step fails: 11 for `pull` through 16 for `push`.
+    pub fn exit_code(&self) -> u8 {
+        10 + Self::all().iter().position(|step| step == self).unwrap() as u8 + 1
+    }
+
+    fn args(&self, message: &str) -> Vec<String> {
+        let args: &[&str] = match self {
+            Self::Pull => &["pull"],
+            //> Honor changes to gitignore
+            Self::Reset => &["rm", "-r", "--force", "--cached", "--quiet", "."],
+            Self::Status => &["status", "--short"],
+            Self::AddAll => &["add", "--all", "."],
+            Self::Commit => &["commit", "--all", "--quiet"],
+            Self::Push => &["push", "--progress"],
+        };
+
+        let mut args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        if *self == Self::Commit {
+            args.push(format!("--message={}", message));
+        }
+        args
+    }
+}
+
+impl fmt::Display for GitUpStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+/// Error raised by [`GitUp::run`], naming the step that stopped the sync.
+#[derive(Debug)]
+pub struct GitUpError {
+    pub step: GitUpStep,
+    pub reason: String,
+}
+
+impl fmt::Display for GitUpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "git up stopped at '{}' |> {}", self.step, self.reason)
+    }
+}
+
+impl std::error::Error for GitUpError {}
+
+#[derive(Debug, Clone)]
+pub struct GitUp {
+    pub message: String,
+    pub repo: Option<PathBuf>,
+}
+
+impl Default for GitUp {
+    fn default() -> Self {
+        Self {
+            message: "Updating".to_string(),
+            repo: None,
+        }
+    }
+}
+
+impl GitUp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        let message = message.into();
+        if !message.trim().is_empty() {
+            self.message = message;
+        }
+        self
+    }
+
+    pub fn with_repo<P: AsRef<Path>>(mut self, repo: P) -> Self {
+        self.repo = Some(repo.as_ref().into());
+        self
+    }
+
+    /// Runs every step from the top level of the repository, stopping at the first failure.
+    pub fn run(&self) -> Result<()> {
+        let top_level = self.top_level()?;
+        let steps = GitUpStep::all();
+
+        for (index, step) in steps.iter().enumerate() {
+            let args = step.args(&self.message);
+            println!(
+                "[{}/{}] {}: git {}",
+                index + 1,
+                steps.len(),
+                step,
+                args.join(" ")
+            );
+
+            if is_dry_run() {
+                continue;
+            }
+
+            if *step == GitUpStep::Commit && !has_staged_changes(&top_level)? {
+                println!("Nothing to commit.");
+                continue;
+            }
+
+            let status = Command::new("git")
+                .args(&args)
+                .current_dir(&top_level)
+                .status()
+                .map_err(|error| GitUpError {
+                    step: *step,
+                    reason: error.to_string(),
+                })?;
+
+            if !status.success() {
+                return Err(GitUpError {
+                    step: *step,
+                    reason: status.to_string(),
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn top_level(&self) -> Result<PathBuf> {
+        match &self.repo {
+            Some(repo) => {
+                let output = Command::new("git")
+                    .args(["rev-parse", "--show-toplevel"])
+                    .current_dir(repo)
+                    .output()?;
+                if output.status.success() {
+                    get_pathbuf(String::from_utf8_lossy(&output.stdout).trim())
+                } else {
+                    Err(anyhow::anyhow!("Not a git repository: {}", repo.display()))
+                }
+            }
+            None => get_git_top_level_dir(),
+        }
+    }
+}
+
+fn has_staged_changes(top_level: &Path) -> Result<bool> {
+    let status = Command::new("git")
+        .args(["diff", "--cached", "--quiet"])
+        .current_dir(top_level)
+        .status()?;
+    Ok(!status.success())
+}
+
+#[test]
+fn test_exit_codes() {
+    assert_eq!(GitUpStep::Pull.exit_code(), 11);
+    assert_eq!(GitUpStep::Push.exit_code(), 16);
+}
+
+#[test]
+fn test_git_up_against_bare_remote() {
+    let temp = tempfile::tempdir().unwrap();
+    let remote = temp.path().join("remote.git");
+    let clone = temp.path().join("clone");
+    let git = |dir: &Path, args: &[&str]| {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    };
+
+    git(temp.path(), &["init", "--quiet", "--bare", "remote.git"]);
+    git(
+        temp.path(),
+        &["clone", "--quiet", remote.to_str().unwrap(), "clone"],
+    );
+    git(&clone, &["config", "user.name", "gitsy"]);
+    git(&clone, &["config", "user.email", "gitsy@example.com"]);
+    std::fs::write(clone.join("README"), "first\n").unwrap();
+    git(&clone, &["add", "README"]);
+    git(&clone, &["commit", "--quiet", "-m", "first"]);
+    git(
+        &clone,
+        &["push", "--quiet", "--set-upstream", "origin", "HEAD"],
+    );
+
+    std::fs::write(clone.join("README"), "second\n").unwrap();
+    GitUp::new()
+        .with_message("Sync")
+        .with_repo(&clone)
+        .run()
+        .unwrap();
+
+    let log = Command::new("git")
+        .args(["log", "-1", "--format=%s"])
+        .current_dir(&remote)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&log.stdout).trim(), "Sync");
+}
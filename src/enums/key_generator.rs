@@ -0,0 +1,518 @@
+use crate::*;
+use anyhow::{anyhow, Context, Result};
+use arboard::Clipboard;
+use ssh_key::{
+    rand_core::{OsRng, RngCore},
+    Algorithm, Cipher, EcdsaCurve, HashAlg, Kdf, KdfAlg, LineEnding, PrivateKey,
+};
+use std::{
+    env, fmt,
+    fs::{read_to_string, remove_file, write},
+    io::{stdin, IsTerminal},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+/// The bcrypt-pbkdf rounds protecting an encrypted key, as `ssh-keygen -a 100`.
+pub const DEFAULT_KDF_ROUNDS: u32 = 100;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    #[default]
+    Ed25519,
+    Ecdsa,
+    Rsa,
+}
+
+impl KeyAlgorithm {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Ed25519 => "ed25519",
+            Self::Ecdsa => "ecdsa",
+            Self::Rsa => "rsa",
+        }
+    }
+
+    /// The key sizes gitsy generates, the first one being the default.
+    pub fn supported_bits(&self) -> &'static [u32] {
+        match self {
+            Self::Ed25519 => &[256],
+            Self::Ecdsa => &[256, 384],
+            Self::Rsa => &[3072, 4096],
+        }
+    }
+}
+
+impl FromStr for KeyAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(algorithm: &str) -> Result<Self> {
+        match algorithm.to_lowercase().as_str() {
+            "ed25519" => Ok(Self::Ed25519),
+            "ecdsa" => Ok(Self::Ecdsa),
+            "rsa" => Ok(Self::Rsa),
+            _ => Err(anyhow!(
+                "Unknown key algorithm '{}' (expected ed25519, ecdsa or rsa)",
+                algorithm
+            )),
+        }
+    }
+}
+
+/// An algorithm with a key size it supports, such as `ecdsa-384` or `rsa-4096`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyType {
+    pub algorithm: KeyAlgorithm,
+    pub bits: u32,
+}
+
+impl KeyType {
+    /// Checks `bits` against the algorithm, taking its default size when there are none.
+    pub fn new(algorithm: KeyAlgorithm, bits: Option<u32>) -> Result<Self> {
+        let supported = algorithm.supported_bits();
+        let bits = bits.unwrap_or(supported[0]);
+        if !supported.contains(&bits) {
+            return Err(anyhow!(
+                "{} keys cannot have {} bits (expected {})",
+                algorithm.to_str(),
+                bits,
+                supported
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            ));
+        }
+        Ok(Self { algorithm, bits })
+    }
+
+    fn ssh_algorithm(&self) -> Algorithm {
+        match (self.algorithm, self.bits) {
+            (KeyAlgorithm::Ed25519, _) => Algorithm::Ed25519,
+            (KeyAlgorithm::Ecdsa, 384) => Algorithm::Ecdsa {
+                curve: EcdsaCurve::NistP384,
+            },
+            (KeyAlgorithm::Ecdsa, _) => Algorithm::Ecdsa {
+                curve: EcdsaCurve::NistP256,
+            },
+            (KeyAlgorithm::Rsa, _) => Algorithm::Rsa { hash: None },
+        }
+    }
+}
+
+impl Default for KeyType {
+    fn default() -> Self {
+        Self {
+            algorithm: KeyAlgorithm::Ed25519,
+            bits: 256,
+        }
+    }
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.algorithm {
+            KeyAlgorithm::Ed25519 => write!(f, "{}", self.algorithm.to_str()),
+            _ => write!(f, "{}-{}", self.algorithm.to_str(), self.bits),
+        }
+    }
+}
+
+impl FromStr for KeyType {
+    type Err = anyhow::Error;
+
+    /// Accepts `<algorithm>` or `<algorithm>-<bits>`.
+    fn from_str(key_type: &str) -> Result<Self> {
+        match key_type.split_once('-') {
+            Some((algorithm, bits)) => {
+                let bits = bits
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid key size '{}' in '{}'", bits, key_type))?;
+                Self::new(algorithm.parse()?, Some(bits))
+            }
+            None => Self::new(key_type.parse()?, None),
+        }
+    }
+}
+
+/// What creates the key pair: gitsy itself, or the `ssh-keygen` binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeygenBackend {
+    Native,
+    SshKeygen,
+}
+
+impl KeygenBackend {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Native => "native",
+            Self::SshKeygen => "ssh-keygen",
+        }
+    }
+
+    /// `ssh-keygen` when it is installed, so that it keeps prompting for the passphrase;
+    /// the native generator otherwise.
+    pub fn detect() -> Self {
+        match find_executable("ssh-keygen") {
+            Some(_) => Self::SshKeygen,
+            None => Self::Native,
+        }
+    }
+}
+
+impl FromStr for KeygenBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(backend: &str) -> Result<Self> {
+        match backend.to_lowercase().as_str() {
+            "native" => Ok(Self::Native),
+            "ssh-keygen" | "openssh" => Ok(Self::SshKeygen),
+            _ => Err(anyhow!(
+                "Unknown key generator '{}' (expected native or ssh-keygen)",
+                backend
+            )),
+        }
+    }
+}
+
+/// Where the passphrase of a new key comes from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PassphraseSource {
+    /// Asked for on the terminal, by gitsy or by `ssh-keygen`.
+    #[default]
+    Prompt,
+    /// Read from an environment variable.
+    Env(String),
+    /// The first line of a file.
+    File(PathBuf),
+    /// No passphrase: the key is written unencrypted.
+    None,
+}
+
+impl PassphraseSource {
+    /// Fails when the passphrase cannot be had, without prompting for it.
+    pub fn check(&self) -> Result<()> {
+        match self {
+            Self::Prompt if !is_dry_run() && !stdin().is_terminal() => Err(anyhow!(
+                "Cannot prompt for a passphrase without a terminal; use an env, file or no passphrase instead"
+            )),
+            Self::Env(_) | Self::File(_) => self.resolve(KeygenBackend::Native).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The passphrase to hand to `backend`, prompting for it if need be.
+    ///
+    /// A prompt is left to `ssh-keygen` itself, and skipped in dry-run mode.
+    pub fn resolve(&self, backend: KeygenBackend) -> Result<Option<String>> {
+        match self {
+            Self::Prompt if is_dry_run() || backend == KeygenBackend::SshKeygen => Ok(None),
+            Self::Prompt => prompt_new_passphrase().map(Some),
+            Self::Env(var) => env::var(var)
+                .map(Some)
+                .with_context(|| format!("The passphrase variable {} is not set", var)),
+            Self::File(path) => read_to_string(path)
+                .map(|content| Some(content.lines().next().unwrap_or_default().to_string()))
+                .with_context(|| format!("Failed to read the passphrase from {}", path.display())),
+            Self::None => Ok(Some(String::new())),
+        }
+    }
+}
+
+impl fmt::Display for PassphraseSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Prompt => write!(f, "prompt"),
+            Self::Env(var) => write!(f, "env:{}", var),
+            Self::File(path) => write!(f, "file:{}", path.display()),
+            Self::None => write!(f, "none"),
+        }
+    }
+}
+
+impl FromStr for PassphraseSource {
+    type Err = anyhow::Error;
+
+    /// Accepts `prompt`, `none`, `env:<VAR>` or `file:<PATH>`.
+    fn from_str(source: &str) -> Result<Self> {
+        match source.split_once(':') {
+            Some(("env", var)) if !var.is_empty() => Ok(Self::Env(var.to_string())),
+            Some(("file", path)) if !path.is_empty() => Ok(Self::File(expand_tilde(path))),
+            None if source.eq_ignore_ascii_case("prompt") => Ok(Self::Prompt),
+            None if source.eq_ignore_ascii_case("none") => Ok(Self::None),
+            _ => Err(anyhow!(
+                "Unknown passphrase source '{}' (expected prompt, none, env:<VAR> or file:<PATH>)",
+                source
+            )),
+        }
+    }
+}
+
+/// Asks for a new passphrase twice, as `ssh-keygen` does; empty means no passphrase.
+fn prompt_new_passphrase() -> Result<String> {
+    for _ in 0..3 {
+        let passphrase =
+            prompt_secret("Enter passphrase for the new key (empty for no passphrase)")?;
+        if prompt_secret("Enter the same passphrase again")? == passphrase {
+            return Ok(passphrase);
+        }
+        println!("Passphrases do not match, try again.");
+    }
+    Err(anyhow!("Passphrases did not match"))
+}
+
+/// Everything needed to generate one key pair.
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeygenOptions {
+    pub key_type: KeyType,
+    pub comment: String,
+    /// `Some("")` writes an unencrypted key. `None` lets `ssh-keygen` prompt for one,
+    /// while the native backend writes the key unencrypted.
+    pub passphrase: Option<String>,
+    pub kdf_rounds: u32,
+    pub backend: KeygenBackend,
+}
+
+impl KeygenOptions {
+    pub fn new(comment: impl Into<String>) -> Self {
+        Self {
+            key_type: KeyType::default(),
+            comment: comment.into(),
+            passphrase: None,
+            kdf_rounds: DEFAULT_KDF_ROUNDS,
+            backend: KeygenBackend::detect(),
+        }
+    }
+
+    /// The backend that generates the pair: the native one whenever there is a passphrase to
+    /// set, as `ssh-keygen` would take it on its command line, where other users can read it.
+    pub fn effective_backend(&self) -> KeygenBackend {
+        match self.passphrase.as_deref() {
+            Some(passphrase) if !passphrase.is_empty() => KeygenBackend::Native,
+            _ => self.backend,
+        }
+    }
+
+    /// Generates the pair into paths that must not exist yet, private key 0600, public key 0644.
+    ///
+    /// Nothing is left behind when generation fails or is cancelled.
+    pub fn generate(&self, private_key: &Path, public_key: &Path) -> Result<()> {
+        if let Some(existing) = [private_key, public_key]
+            .into_iter()
+            .find(|key| key.exists())
+        {
+            return Err(anyhow!("{} already exists", existing.display()));
+        }
+
+        let generated = match self.effective_backend() {
+            KeygenBackend::Native => self.generate_native(private_key, public_key),
+            KeygenBackend::SshKeygen => self.generate_with_ssh_keygen(private_key, public_key),
+        };
+        if let Err(error) = generated {
+            let _ = remove_file(private_key);
+            let _ = remove_file(public_key);
+            return Err(error);
+        }
+        set_mode(private_key, PRIVATE_FILE_MODE)?;
+        set_mode(public_key, PUBLIC_KEY_MODE)?;
+        Ok(())
+    }
+
+    fn generate_native(&self, private_key: &Path, public_key: &Path) -> Result<()> {
+        let mut rng = OsRng;
+        let mut key = match self.key_type.algorithm {
+            KeyAlgorithm::Rsa => PrivateKey::from(ssh_key::private::RsaKeypair::random(
+                &mut rng,
+                self.key_type.bits as usize,
+            )?),
+            _ => PrivateKey::random(&mut rng, self.key_type.ssh_algorithm())?,
+        };
+        key.set_comment(&self.comment);
+        let public = key.public_key().to_openssh()?;
+
+        let key = match self.passphrase.as_deref() {
+            Some(passphrase) if !passphrase.is_empty() => {
+                let Kdf::Bcrypt { salt, .. } = Kdf::new(KdfAlg::Bcrypt, &mut rng)? else {
+                    return Err(anyhow!("Unexpected KDF for the key encryption"));
+                };
+                let kdf = Kdf::Bcrypt {
+                    salt,
+                    rounds: self.kdf_rounds,
+                };
+                key.encrypt_with(Cipher::Aes256Ctr, kdf, rng.next_u32(), passphrase)?
+            }
+            _ => key,
+        };
+
+        write_private_file(private_key, &key.to_openssh(LineEnding::LF)?)
+            .with_context(|| format!("Failed to write {}", private_key.display()))?;
+        write(public_key, format!("{}\n", public))
+            .with_context(|| format!("Failed to write {}", public_key.display()))?;
+        Ok(())
+    }
+
+    fn generate_with_ssh_keygen(&self, private_key: &Path, public_key: &Path) -> Result<()> {
+        let mut command = Command::new("ssh-keygen");
+        command
+            .arg("-t")
+            .arg(self.key_type.algorithm.to_str())
+            .arg("-a")
+            .arg(self.kdf_rounds.to_string())
+            .arg("-f")
+            .arg(private_key)
+            .arg("-C")
+            .arg(&self.comment);
+        if self.key_type.algorithm != KeyAlgorithm::Ed25519 {
+            command.arg("-b").arg(self.key_type.bits.to_string());
+        }
+        //> Only an empty passphrase gets here; without one, ssh-keygen prompts for it
+        if self.passphrase.is_some() {
+            command.arg("-N").arg("");
+        }
+
+        let cmd_keygen = command
+            .stdin(Stdio::inherit()) // Inherit stdin for password prompt
+            .stdout(Stdio::inherit()) // Inherit stdout for password confirmation
+            .stderr(Stdio::inherit()) // Inherit stderr for error messages
+            .output()
+            .context("Failed to run ssh-keygen; is it installed? (see also the native backend)")?;
+
+        if cmd_keygen.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "SSH Keygen Error |> {} |> {}",
+                cmd_keygen.status,
+                String::from_utf8_lossy(&cmd_keygen.stderr)
+            ))
+        }
+    }
+}
+
+impl fmt::Debug for KeygenOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeygenOptions")
+            .field("key_type", &self.key_type)
+            .field("comment", &self.comment)
+            .field(
+                "passphrase",
+                &self.passphrase.as_ref().map(|_| "<redacted>"),
+            )
+            .field("kdf_rounds", &self.kdf_rounds)
+            .field("backend", &self.backend)
+            .finish()
+    }
+}
+
+/// Copies a new public key to the clipboard, or prints it where there is none (SSH, CI).
+pub(crate) fn share_public_key(public_key: &Path) -> Result<()> {
+    let public_key_text = read_to_string(public_key)?.trim().to_string();
+    let copied = Clipboard::new().and_then(|mut clipboard| clipboard.set_text(&public_key_text));
+
+    match copied {
+        Ok(()) => println!(
+            "SSH keys generated successfully and the public key has been copied to the clipboard."
+        ),
+        Err(error) => println!(
+            "SSH keys generated successfully. The clipboard is unavailable ({}), so here is the public key:\n{}",
+            error, public_key_text
+        ),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_key_type_from_str() {
+    assert_eq!("ed25519".parse::<KeyType>().unwrap(), KeyType::default());
+    assert_eq!(
+        "ecdsa-384".parse::<KeyType>().unwrap(),
+        KeyType {
+            algorithm: KeyAlgorithm::Ecdsa,
+            bits: 384
+        }
+    );
+    assert_eq!("rsa".parse::<KeyType>().unwrap().bits, 3072);
+    assert!("rsa-2048".parse::<KeyType>().is_err());
+    assert!("dsa".parse::<KeyType>().is_err());
+    assert_eq!(
+        KeyType::new(KeyAlgorithm::Rsa, Some(4096))
+            .unwrap()
+            .to_string(),
+        "rsa-4096"
+    );
+}
+
+#[test]
+fn test_generate_native() {
+    let temp = tempfile::tempdir().unwrap();
+    let private_key = temp.path().join("id");
+    let public_key = temp.path().join("id.pub");
+
+    let options = KeygenOptions {
+        key_type: "ecdsa-256".parse().unwrap(),
+        passphrase: Some("correct horse".to_string()),
+        kdf_rounds: 2,
+        backend: KeygenBackend::Native,
+        ..KeygenOptions::new("jane@example.com")
+    };
+    let ssh_keygen = KeygenOptions {
+        backend: KeygenBackend::SshKeygen,
+        ..options.clone()
+    };
+    assert_eq!(ssh_keygen.effective_backend(), KeygenBackend::Native);
+    assert_eq!(
+        KeygenOptions {
+            passphrase: Some(String::new()),
+            ..ssh_keygen.clone()
+        }
+        .effective_backend(),
+        KeygenBackend::SshKeygen
+    );
+
+    ssh_keygen.generate(&private_key, &public_key).unwrap();
+    assert!(options.generate(&private_key, &public_key).is_err());
+
+    let key = PrivateKey::read_openssh_file(&private_key).unwrap();
+    assert!(key.is_encrypted());
+    assert!(matches!(key.kdf(), Kdf::Bcrypt { rounds: 2, .. }));
+    let key = key.decrypt("correct horse").unwrap();
+    assert_eq!(key.comment(), "jane@example.com");
+    assert_eq!(
+        read_to_string(&public_key).unwrap().trim(),
+        key.public_key().to_openssh().unwrap()
+    );
+    #[cfg(unix)]
+    assert_eq!(mode_of(&private_key), Some(PRIVATE_FILE_MODE));
+    assert!(!key.fingerprint(HashAlg::Sha256).to_string().is_empty());
+}
+
+#[test]
+fn test_passphrase_source() {
+    assert_eq!(
+        "prompt".parse::<PassphraseSource>().unwrap(),
+        PassphraseSource::Prompt
+    );
+    assert_eq!(
+        "env:KEY_PASS".parse::<PassphraseSource>().unwrap(),
+        PassphraseSource::Env("KEY_PASS".into())
+    );
+    assert!("env:".parse::<PassphraseSource>().is_err());
+    assert!("vault:key".parse::<PassphraseSource>().is_err());
+
+    let temp = tempfile::tempdir().unwrap();
+    let file = temp.path().join("passphrase");
+    write(&file, "correct horse\nignored\n").unwrap();
+    let source: PassphraseSource = format!("file:{}", file.display()).parse().unwrap();
+    assert_eq!(
+        source.resolve(KeygenBackend::SshKeygen).unwrap().as_deref(),
+        Some("correct horse")
+    );
+    assert_eq!(
+        PassphraseSource::None
+            .resolve(KeygenBackend::Native)
+            .unwrap()
+            .as_deref(),
+        Some("")
+    );
+}
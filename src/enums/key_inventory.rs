@@ -0,0 +1,398 @@
+use crate::*;
+use anyhow::Result;
+use serde::Serialize;
+use ssh_key::{public::KeyData, Algorithm, EcdsaCurve, HashAlg, Mpint, PrivateKey, PublicKey};
+use std::{
+    fs::{canonicalize, read_to_string},
+    path::{Path, PathBuf},
+};
+
+/// The keys ssh tries on its own, without an `IdentityFile`, when they are in `~/.ssh`.
+const DEFAULT_IDENTITIES: [&str; 7] = [
+    "id_rsa",
+    "id_ecdsa",
+    "id_ecdsa_sk",
+    "id_ed25519",
+    "id_ed25519_sk",
+    "id_xmss",
+    "id_dsa",
+];
+
+/// Something off about a key in the SSH directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyFlag {
+    /// Neither a `Host` block, a profile nor ssh's defaults use the key.
+    Orphaned,
+    MissingPublicKey,
+    MissingPrivateKey,
+}
+
+impl KeyFlag {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Orphaned => "orphaned",
+            Self::MissingPublicKey => "no .pub",
+            Self::MissingPrivateKey => "no private key",
+        }
+    }
+}
+
+/// A key pair found in the SSH directory, or the half of one that is there.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SshKeyEntry {
+    pub private_key: Option<PathBuf>,
+    pub public_key: Option<PathBuf>,
+    pub algorithm: Option<String>,
+    pub bits: Option<u32>,
+    pub fingerprint: Option<String>,
+    pub comment: Option<String>,
+    /// `None` without a private key, or when its format does not say.
+    pub encrypted: Option<bool>,
+    /// The patterns of the `Host` blocks whose `IdentityFile` is this key.
+    pub hosts: Vec<String>,
+    pub profiles: Vec<String>,
+    /// One of the keys ssh tries without being told to.
+    pub default_identity: bool,
+    pub flags: Vec<KeyFlag>,
+    /// Why neither half of the pair could be parsed.
+    pub error: Option<String>,
+}
+
+impl SshKeyEntry {
+    /// Every key under `get_ssh_home`, with the `Host` blocks of its config and the profiles using it.
+    pub fn scan_all() -> Result<Vec<Self>> {
+        let ssh_dir = get_ssh_home()?;
+        let ssh_config = SshConfig::load(ssh_dir.join("config"))?;
+        let profiles: Vec<(String, PathBuf)> = ProfileStore::load()?
+            .profiles
+            .into_iter()
+            .filter_map(|(id, profile)| Some((id, profile.to_git_init().key_paths().ok()?.0)))
+            .collect();
+        Ok(Self::scan(&ssh_dir, &ssh_config, &profiles))
+    }
+
+    /// Every key under `ssh_dir`; `profiles` pairs each profile with its private key.
+    pub fn scan(
+        ssh_dir: &Path,
+        ssh_config: &SshConfig,
+        profiles: &[(String, PathBuf)],
+    ) -> Vec<Self> {
+        let identity_files = identity_files(ssh_config);
+        let files = list_ssh_files(ssh_dir);
+
+        let mut keys = vec![];
+        for file in &files {
+            let public_key = with_suffix(file, "pub");
+            let (path, mut key) = if is_private_key(file) {
+                let public_key = public_key.is_file().then_some(&public_key);
+                (file.clone(), Self::read(Some(file), public_key))
+            } else if file.extension().is_some_and(|extension| extension == "pub")
+                && !is_private_key(file.with_extension(""))
+            {
+                (file.with_extension(""), Self::read(None, Some(file)))
+            } else {
+                continue;
+            };
+
+            //> Who uses the key
+            let key_path = canonical(&path);
+            key.hosts = identity_files
+                .iter()
+                .filter(|(_, identity_file)| key_path == canonical(identity_file))
+                .map(|(host, _)| host.clone())
+                .collect();
+            key.hosts.dedup();
+            key.profiles = profiles
+                .iter()
+                .filter(|(_, private_key)| key_path == canonical(private_key))
+                .map(|(id, _)| id.clone())
+                .collect();
+            key.default_identity = path.parent() == Some(ssh_dir)
+                && path
+                    .file_name()
+                    .is_some_and(|name| DEFAULT_IDENTITIES.iter().any(|default| name == *default));
+
+            //> What is off about it
+            if key.hosts.is_empty() && key.profiles.is_empty() && !key.default_identity {
+                key.flags.push(KeyFlag::Orphaned);
+            }
+            if key.public_key.is_none() {
+                key.flags.push(KeyFlag::MissingPublicKey);
+            }
+            if key.private_key.is_none() {
+                key.flags.push(KeyFlag::MissingPrivateKey);
+            }
+            keys.push(key);
+        }
+        keys
+    }
+
+    /// Reads what it can from either half of the pair, preferring the public key, whose
+    /// comment is readable even when the private key is encrypted.
+    fn read(private_key: Option<&PathBuf>, public_key: Option<&PathBuf>) -> Self {
+        let mut key = Self {
+            private_key: private_key.cloned(),
+            public_key: public_key.cloned(),
+            ..Self::default()
+        };
+
+        let mut errors = vec![];
+        let mut public = match public_key.map(|path| PublicKey::read_openssh_file(path)) {
+            Some(Ok(public)) => Some(public),
+            Some(Err(error)) => {
+                errors.push(format!("{}: {}", tilde_path(public_key.unwrap()), error));
+                None
+            }
+            None => None,
+        };
+
+        if let Some(private_key) = private_key {
+            match read_to_string(private_key) {
+                Ok(content) => match PrivateKey::from_openssh(&content) {
+                    Ok(private) => {
+                        key.encrypted = Some(private.is_encrypted());
+                        public.get_or_insert_with(|| private.public_key().clone());
+                    }
+                    //> Legacy PEM keys name their algorithm and encryption in the header
+                    Err(_) if !content.starts_with("-----BEGIN OPENSSH") => {
+                        key.encrypted = Some(content.contains("ENCRYPTED"));
+                        key.algorithm = [("RSA", "rsa"), ("EC", "ecdsa"), ("DSA", "dsa")]
+                            .into_iter()
+                            .find(|(label, _)| {
+                                content.starts_with(&format!("-----BEGIN {} PRIVATE KEY", label))
+                            })
+                            .map(|(_, algorithm)| algorithm.to_string());
+                    }
+                    Err(error) => errors.push(format!("{}: {}", tilde_path(private_key), error)),
+                },
+                Err(error) => errors.push(format!("{}: {}", tilde_path(private_key), error)),
+            }
+        }
+
+        match public {
+            Some(public) => {
+                key.algorithm = Some(algorithm_name(&public.algorithm()));
+                key.bits = key_bits(public.key_data());
+                key.fingerprint = Some(public.fingerprint(HashAlg::Sha256).to_string());
+                key.comment = Some(public.comment().to_string()).filter(|c| !c.is_empty());
+            }
+            None if !errors.is_empty() => key.error = Some(errors.join("; ")),
+            None => {}
+        }
+        key
+    }
+
+    /// The algorithm with its size, like `KeyType`: `ed25519`, `ecdsa-384` or `rsa-4096`.
+    pub fn key_type(&self) -> String {
+        match (self.algorithm.as_deref(), self.bits) {
+            (Some("ed25519"), _) | (Some(_), None) => self.algorithm.clone().unwrap_or_default(),
+            (Some(algorithm), Some(bits)) => format!("{}-{}", algorithm, bits),
+            (None, _) => "?".to_string(),
+        }
+    }
+
+    /// The `Host` blocks and profiles using the key, for the table.
+    pub fn used_by(&self) -> String {
+        let mut users: Vec<String> = self
+            .hosts
+            .iter()
+            .map(|host| format!("Host {}", host))
+            .chain(
+                self.profiles
+                    .iter()
+                    .map(|profile| format!("profile {}", profile)),
+            )
+            .collect();
+        if self.default_identity {
+            users.push("ssh default".to_string());
+        }
+        users.join(", ")
+    }
+}
+
+/// Prints `keys` as a table, one row per key.
+pub fn print_key_table(keys: &[SshKeyEntry]) {
+    let rows: Vec<Vec<String>> = keys
+        .iter()
+        .map(|key| {
+            let path = key.private_key.as_ref().or(key.public_key.as_ref());
+            vec![
+                path.map(tilde_path).unwrap_or_default(),
+                key.key_type(),
+                key.fingerprint.clone().unwrap_or_default(),
+                key.comment.clone().unwrap_or_default(),
+                match key.encrypted {
+                    Some(true) => "yes",
+                    Some(false) => "no",
+                    None => "-",
+                }
+                .to_string(),
+                key.used_by(),
+                key.flags
+                    .iter()
+                    .map(KeyFlag::to_str)
+                    .chain(key.error.as_ref().map(|_| "unreadable"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ]
+        })
+        .collect();
+    print_table(
+        &[
+            "KEY",
+            "TYPE",
+            "FINGERPRINT",
+            "COMMENT",
+            "ENCRYPTED",
+            "USED BY",
+            "FLAGS",
+        ],
+        &rows,
+    );
+}
+
+/// The `IdentityFile` of every `Host` block, and of the lines before the first block as `*`.
+fn identity_files(ssh_config: &SshConfig) -> Vec<(String, PathBuf)> {
+    let global = ssh_config
+        .preamble
+        .iter()
+        .filter_map(SshConfigLine::directive)
+        .map(|directive| ("*".to_string(), directive));
+    let blocks = ssh_config
+        .blocks
+        .iter()
+        .filter(|block| block.kind == SshBlockKind::Host)
+        .flat_map(|block| {
+            let host = block.patterns().join(" ");
+            block
+                .directives()
+                .map(move |directive| (host.clone(), directive))
+        });
+
+    global
+        .chain(blocks)
+        .filter(|(_, (key, _))| key.eq_ignore_ascii_case("IdentityFile"))
+        .map(|(host, (_, value))| (host, expand_identity_file(value)))
+        .collect()
+}
+
+/// Expands `~` and `%d` in an `IdentityFile`; relative paths are taken from the home directory.
+fn expand_identity_file(value: &str) -> PathBuf {
+    let value = value.trim_matches('"');
+    let Some(home) = dirs::home_dir() else {
+        return PathBuf::from(value);
+    };
+    let path = expand_tilde(&value.replace("%d", &home.to_string_lossy()));
+    match path.is_absolute() {
+        true => path,
+        false => home.join(path),
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn algorithm_name(algorithm: &Algorithm) -> String {
+    match algorithm {
+        Algorithm::Ed25519 => KeyAlgorithm::Ed25519.to_str().to_string(),
+        Algorithm::Ecdsa { .. } => KeyAlgorithm::Ecdsa.to_str().to_string(),
+        Algorithm::Rsa { .. } => KeyAlgorithm::Rsa.to_str().to_string(),
+        Algorithm::Dsa => "dsa".to_string(),
+        other => other.as_str().to_string(),
+    }
+}
+
+fn key_bits(key_data: &KeyData) -> Option<u32> {
+    let mpint_bits = |mpint: &Mpint| {
+        let bytes = mpint.as_positive_bytes()?;
+        let first = bytes.first()?;
+        Some(bytes.len() as u32 * 8 - first.leading_zeros())
+    };
+
+    match key_data {
+        KeyData::Ed25519(_) | KeyData::SkEd25519(_) => Some(256),
+        KeyData::Ecdsa(ecdsa) => Some(match ecdsa.curve() {
+            EcdsaCurve::NistP256 => 256,
+            EcdsaCurve::NistP384 => 384,
+            EcdsaCurve::NistP521 => 521,
+        }),
+        KeyData::SkEcdsaSha2NistP256(_) => Some(256),
+        KeyData::Rsa(rsa) => mpint_bits(&rsa.n),
+        KeyData::Dsa(dsa) => mpint_bits(&dsa.p),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_scan_keys() {
+    let temp = tempfile::tempdir().unwrap();
+    let ssh_dir = temp.path().join(".ssh");
+    let generate = |path: &Path, key_type: &str, passphrase: &str| {
+        create_private_dir(path.parent().unwrap()).unwrap();
+        KeygenOptions {
+            key_type: key_type.parse().unwrap(),
+            passphrase: Some(passphrase.to_string()),
+            kdf_rounds: 2,
+            backend: KeygenBackend::Native,
+            ..KeygenOptions::new("jane@example.com")
+        }
+        .generate(path, &with_suffix(path, "pub"))
+        .unwrap();
+    };
+
+    let work = ssh_dir.join("github.com").join("work");
+    let personal = ssh_dir.join("github.com").join("jane");
+    let default = ssh_dir.join("id_ed25519");
+    let old = ssh_dir.join("old");
+    generate(&work, "ecdsa-384", "secret");
+    generate(&personal, "ed25519", "");
+    generate(&default, "ed25519", "");
+    generate(&old, "ed25519", "");
+    std::fs::remove_file(with_suffix(&personal, "pub")).unwrap();
+    std::fs::remove_file(&old).unwrap();
+    std::fs::write(ssh_dir.join("known_hosts"), "").unwrap();
+
+    let ssh_config = SshConfig::parse(&format!(
+        "Host github.com-work\n\tIdentityFile {}\n",
+        work.display()
+    ));
+    let profiles = [("personal".to_string(), personal.clone())];
+    let keys = SshKeyEntry::scan(&ssh_dir, &ssh_config, &profiles);
+    assert_eq!(keys.len(), 4);
+
+    let key = |path: &Path| {
+        keys.iter()
+            .find(|key| {
+                key.private_key.as_deref() == Some(path)
+                    || key.public_key.as_deref() == Some(&with_suffix(path, "pub"))
+            })
+            .unwrap()
+    };
+    let work_key = key(&work);
+    assert_eq!(work_key.key_type(), "ecdsa-384");
+    assert_eq!(work_key.encrypted, Some(true));
+    assert_eq!(work_key.comment.as_deref(), Some("jane@example.com"));
+    assert!(work_key
+        .fingerprint
+        .as_ref()
+        .unwrap()
+        .starts_with("SHA256:"));
+    assert_eq!(work_key.hosts, ["github.com-work"]);
+    assert!(work_key.flags.is_empty());
+
+    let personal_key = key(&personal);
+    assert_eq!(personal_key.key_type(), "ed25519");
+    assert_eq!(personal_key.encrypted, Some(false));
+    assert_eq!(personal_key.profiles, ["personal"]);
+    assert_eq!(personal_key.flags, [KeyFlag::MissingPublicKey]);
+
+    assert!(key(&default).default_identity);
+    assert!(key(&default).flags.is_empty());
+    assert_eq!(
+        key(&old).flags,
+        [KeyFlag::Orphaned, KeyFlag::MissingPrivateKey]
+    );
+    assert_eq!(key(&old).encrypted, None);
+}
@@ -0,0 +1,318 @@
+use crate::*;
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::{create_dir_all, read_to_string, write},
+    path::{Path, PathBuf},
+};
+
+/// A key pair being replaced: the new pair sits next to the old one, which is archived with a
+/// date suffix until the rotation is finalized or aborted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRotation {
+    pub started: String,
+    /// Where the old pair was, and where aborting puts it back.
+    pub private_key: PathBuf,
+    pub public_key: PathBuf,
+    pub new_private_key: PathBuf,
+    pub new_public_key: PathBuf,
+    pub archived_private_key: PathBuf,
+    /// `None` when the old private key had no public key next to it.
+    pub archived_public_key: Option<PathBuf>,
+    pub config_file: PathBuf,
+    pub host: String,
+    /// The `IdentityFile` of the host block before the rotation, `None` when it had none.
+    pub old_identity_file: Option<String>,
+    /// Whether the rotation added the host block, so that aborting removes it again.
+    pub created_host: bool,
+}
+
+impl KeyRotation {
+    /// Deletes the archived old pair; the new pair and the host's `IdentityFile` stay as they are.
+    pub fn plan_finalize(&self) -> Plan {
+        let mut actions = vec![];
+        if self.archived_private_key.exists() {
+            actions.push(PlannedAction::DeleteKey {
+                path: self.archived_private_key.clone(),
+            });
+        }
+        if let Some(path) = self
+            .archived_public_key
+            .as_ref()
+            .filter(|path| path.exists())
+        {
+            actions.push(PlannedAction::DeleteFile { path: path.clone() });
+        }
+        Plan::from(actions)
+    }
+
+    /// Deletes the new pair, moves the archived one back and restores the host's `IdentityFile`.
+    pub fn plan_abort(&self) -> Result<Plan> {
+        if !self.archived_private_key.exists() {
+            return Err(anyhow!(
+                "The archived key {} is gone, there is nothing to restore",
+                tilde_path(&self.archived_private_key)
+            ));
+        }
+
+        //> Delete the new pair
+        let mut actions: Vec<PlannedAction> = [&self.new_private_key, &self.new_public_key]
+            .into_iter()
+            .filter(|key| key.exists())
+            .map(|key| PlannedAction::DeleteKey { path: key.clone() })
+            .collect();
+
+        //> Move the old pair back
+        actions.push(PlannedAction::MoveFile {
+            from: self.archived_private_key.clone(),
+            to: self.private_key.clone(),
+        });
+        if let Some(path) = self
+            .archived_public_key
+            .as_ref()
+            .filter(|path| path.exists())
+        {
+            actions.push(PlannedAction::MoveFile {
+                from: path.clone(),
+                to: self.public_key.clone(),
+            });
+        }
+
+        //> Point the host back at the old identity
+        let mut ssh_config = SshConfig::load(&self.config_file)?;
+        if self.created_host {
+            ssh_config.remove_host(&self.host);
+        } else if let Some(block) = ssh_config.find_host_mut(&self.host) {
+            match &self.old_identity_file {
+                Some(identity_file) => block.set("IdentityFile", identity_file),
+                None => block.unset("IdentityFile"),
+            };
+        }
+        actions.extend(ssh_config.plan_save(&self.config_file)?);
+
+        Ok(Plan::from(actions))
+    }
+}
+
+impl fmt::Display for KeyRotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (Host {}), replaced by {} and archived at {} since {}",
+            tilde_path(&self.private_key),
+            self.host,
+            tilde_path(&self.new_private_key),
+            tilde_path(&self.archived_private_key),
+            self.started
+        )
+    }
+}
+
+/// `path` with `.suffix` appended to its file name, such as `jane.pub` for `jane`.
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(".");
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Where the pair at `private_key` is archived: the key name with today's date, and a
+/// counter when that is taken already.
+pub(crate) fn archive_path(private_key: &Path) -> PathBuf {
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    let mut archive = with_suffix(private_key, &date);
+    let mut counter = 1;
+    while archive.exists() || with_suffix(&archive, "pub").exists() {
+        counter += 1;
+        archive = with_suffix(private_key, &format!("{}-{}", date, counter));
+    }
+    archive
+}
+
+/// Where the pair replacing `private_key` is generated: the key name with the first free
+/// counter, such as `jane-2`.
+pub(crate) fn rotated_path(private_key: &Path) -> PathBuf {
+    let mut counter = 2;
+    loop {
+        let mut path = private_key.as_os_str().to_os_string();
+        path.push(format!("-{}", counter));
+        let path = PathBuf::from(path);
+        if !path.exists() && !with_suffix(&path, "pub").exists() {
+            return path;
+        }
+        counter += 1;
+    }
+}
+
+/// The rotations in progress, kept in `key-rotations.toml` under the gitsy state directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRotations {
+    #[serde(skip)]
+    pub path: PathBuf,
+    #[serde(default, rename = "rotation")]
+    pub rotations: Vec<KeyRotation>,
+}
+
+impl KeyRotations {
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(get_state_dir()?.join("key-rotations.toml"))
+    }
+
+    pub fn load() -> Result<Self> {
+        Self::load_from(Self::default_path()?)
+    }
+
+    /// Reads the rotations at `path`, starting empty if the file does not exist yet.
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut store: Self = if path.exists() {
+            let content = read_to_string(path)?;
+            toml::from_str(&content)
+                .with_context(|| format!("Failed to parse key rotations from {}", path.display()))?
+        } else {
+            Self::default()
+        };
+        store.path = path.to_path_buf();
+        Ok(store)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.exists() {
+                create_dir_all(parent)?;
+            }
+        }
+        write(&self.path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, rotation: KeyRotation) -> Result<()> {
+        if self.get(&rotation.private_key).is_some() {
+            return Err(anyhow!(
+                "A rotation of {} is in progress already; finalize or abort it first",
+                tilde_path(&rotation.private_key)
+            ));
+        }
+        self.rotations.push(rotation);
+        Ok(())
+    }
+
+    /// The rotation that `private_key` is the old or the new key of.
+    pub fn get(&self, private_key: &Path) -> Option<&KeyRotation> {
+        self.rotations.iter().find(|rotation| {
+            rotation.private_key == private_key || rotation.new_private_key == private_key
+        })
+    }
+
+    /// The rotation of `private_key`, or the only one in progress when no key is given.
+    pub fn find(&self, private_key: Option<&Path>) -> Result<&KeyRotation> {
+        match (private_key, self.rotations.as_slice()) {
+            (Some(private_key), _) => self
+                .get(private_key)
+                .ok_or_else(|| anyhow!("No rotation of {} in progress", tilde_path(private_key))),
+            (None, [rotation]) => Ok(rotation),
+            (None, []) => Err(anyhow!("No key rotation in progress")),
+            (None, _) => Err(anyhow!(
+                "Several key rotations are in progress, name the key: {}",
+                self.rotations
+                    .iter()
+                    .map(|rotation| tilde_path(&rotation.private_key))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    pub fn remove(&mut self, private_key: &Path) {
+        self.rotations
+            .retain(|rotation| rotation.private_key != private_key);
+    }
+}
+
+#[test]
+fn test_rotate_abort_and_finalize() {
+    let temp = tempfile::tempdir().unwrap();
+    let ssh_dir = temp.path().join(".ssh");
+    let git_init = GitInit::new()
+        .with_name("jane")
+        .with_host("example.com")
+        .with_ssh_dir(&ssh_dir)
+        .with_key_backend(KeygenBackend::Native)
+        .with_passphrase(PassphraseSource::None);
+
+    let private_key = ssh_dir.join("example.com").join("jane");
+    let public_key = private_key.with_extension("pub");
+    let config_file = ssh_dir.join("config");
+    create_private_dir(private_key.parent().unwrap()).unwrap();
+    KeygenOptions {
+        passphrase: Some(String::new()),
+        backend: KeygenBackend::Native,
+        ..KeygenOptions::new("old")
+    }
+    .generate(&private_key, &public_key)
+    .unwrap();
+    let old_key = read_to_string(&private_key).unwrap();
+    let old_config = format!(
+        "Host example.com\n\tUser jane\n\tIdentityFile {}\n",
+        private_key.display()
+    );
+    write(&config_file, &old_config).unwrap();
+    let identity_file = || {
+        SshConfig::load(&config_file)
+            .unwrap()
+            .find_host("example.com")
+            .unwrap()
+            .get("IdentityFile")
+            .map(PathBuf::from)
+    };
+
+    let mut store = KeyRotations::load_from(temp.path().join("key-rotations.toml")).unwrap();
+    let (plan, rotation) = git_init.clone().plan_rotation(&store).unwrap();
+    assert_eq!(read_to_string(&private_key).unwrap(), old_key);
+    store.add(rotation.clone()).unwrap();
+    store.save().unwrap();
+    plan.apply().unwrap();
+
+    //> The new pair sits next to the old one, which is archived, and the host uses it
+    assert_eq!(
+        rotation.new_private_key,
+        ssh_dir.join("example.com").join("jane-2")
+    );
+    assert!(rotation.new_public_key.exists());
+    assert!(!private_key.exists());
+    assert_eq!(
+        read_to_string(&rotation.archived_private_key).unwrap(),
+        old_key
+    );
+    assert!(rotation.archived_public_key.as_ref().unwrap().exists());
+    assert_eq!(identity_file(), Some(rotation.new_private_key.clone()));
+
+    //> A second rotation of the key waits for this one
+    let store = KeyRotations::load_from(&store.path).unwrap();
+    let error = git_init.clone().plan_rotation(&store).unwrap_err();
+    assert!(error.to_string().contains("in progress"), "{}", error);
+
+    //> Aborting puts everything back
+    let rotation = store.find(None).unwrap();
+    rotation.plan_abort().unwrap().apply().unwrap();
+    assert_eq!(read_to_string(&private_key).unwrap(), old_key);
+    assert!(public_key.exists());
+    assert!(!rotation.new_private_key.exists());
+    assert!(!rotation.archived_private_key.exists());
+    assert_eq!(read_to_string(&config_file).unwrap(), old_config);
+
+    //> Finalizing drops the old pair only, and the next rotation starts from the new key
+    let store = KeyRotations::load_from(temp.path().join("other.toml")).unwrap();
+    let (plan, rotation) = git_init.clone().plan_rotation(&store).unwrap();
+    plan.apply().unwrap();
+    rotation.plan_finalize().apply().unwrap();
+    assert!(!rotation.archived_private_key.exists());
+    assert!(!rotation.archived_public_key.unwrap().exists());
+    assert!(rotation.new_private_key.exists());
+    assert_eq!(identity_file(), Some(rotation.new_private_key.clone()));
+
+    let (_, next) = git_init.clone().plan_rotation(&store).unwrap();
+    assert_eq!(next.private_key, rotation.new_private_key);
+}
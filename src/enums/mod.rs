@@ -1,7 +1,45 @@
+mod config_diff;
+mod config_document;
+mod config_explanation;
+mod config_lint;
+mod config_preset;
+mod config_snapshot;
 mod git_config;
+mod git_config_backend;
+mod git_config_entry;
+mod git_config_file;
 mod git_init;
+mod git_up;
+mod health_check;
+mod key_generator;
+mod key_inventory;
+mod key_rotation;
+mod plan;
+mod profile;
+mod profile_binding;
 mod prompts;
+mod remote_url;
+mod ssh_config;
 
+pub use config_diff::*;
+pub use config_document::*;
+pub use config_explanation::*;
+pub use config_lint::*;
+pub use config_preset::*;
+pub use config_snapshot::*;
 pub use git_config::*;
+pub use git_config_backend::*;
+pub use git_config_entry::*;
+pub use git_config_file::*;
 pub use git_init::*;
+pub use git_up::*;
+pub use health_check::*;
+pub use key_generator::*;
+pub use key_inventory::*;
+pub use key_rotation::*;
+pub use plan::*;
+pub use profile::*;
+pub use profile_binding::*;
 pub use prompts::*;
+pub use remote_url::*;
+pub use ssh_config::*;
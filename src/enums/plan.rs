@@ -0,0 +1,398 @@
+use crate::*;
+use anyhow::{anyhow, Result};
+use serde::{Serialize, Serializer};
+use similar::TextDiff;
+use std::{
+    fmt,
+    fs::{create_dir_all, read_to_string, remove_file, rename, write},
+    path::{Path, PathBuf},
+};
+
+/// A single change `GitInit` intends to make, as shown in plan mode and applied otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedAction {
+    CreateFile {
+        path: PathBuf,
+        content: String,
+    },
+    ModifyFile {
+        path: PathBuf,
+        old: String,
+        new: String,
+    },
+    GenerateKey {
+        private_key: PathBuf,
+        public_key: PathBuf,
+        options: KeygenOptions,
+    },
+    /// Moves the existing keys to `backup` and the freshly generated pair into their place,
+    /// all or nothing.
+    ReplaceKey {
+        private_key: PathBuf,
+        public_key: PathBuf,
+        new_private_key: PathBuf,
+        new_public_key: PathBuf,
+        backup: PathBuf,
+    },
+    DeleteKey {
+        path: PathBuf,
+    },
+    DeleteFile {
+        path: PathBuf,
+    },
+    /// Renames a file within its filesystem, such as a key pair into or out of its archive.
+    MoveFile {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// Sets the Unix permission bits of a file or directory; a no-op elsewhere.
+    SetPermissions {
+        path: PathBuf,
+        mode: u32,
+    },
+    SetConfig {
+        scope: GitConfig,
+        key: String,
+        old: Option<String>,
+        new: String,
+    },
+    /// Sets a key without a value, as in `[core] bare`, which git reads as `true`.
+    SetBareConfig {
+        scope: GitConfig,
+        key: String,
+        old: Option<String>,
+    },
+    UnsetConfig {
+        scope: GitConfig,
+        key: String,
+        old: String,
+    },
+    UnsetAllConfig {
+        scope: GitConfig,
+        key: String,
+        old: Vec<String>,
+    },
+    /// Adds another value to a multivar key.
+    AddConfig {
+        scope: GitConfig,
+        key: String,
+        value: String,
+    },
+    SetRemote {
+        remote: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl PlannedAction {
+    /// The action that makes `path` hold `content`, or `None` if it already does.
+    pub fn write_file(path: &Path, content: String) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(Some(Self::CreateFile {
+                path: path.to_path_buf(),
+                content,
+            }));
+        }
+
+        let old = read_to_string(path)?;
+        if old == content {
+            Ok(None)
+        } else {
+            Ok(Some(Self::ModifyFile {
+                path: path.to_path_buf(),
+                old,
+                new: content,
+            }))
+        }
+    }
+
+    pub fn apply(&self) -> Result<()> {
+        match self {
+            Self::CreateFile { path, content }
+            | Self::ModifyFile {
+                path, new: content, ..
+            } if is_in_ssh_home(path) => {
+                if let Some(parent) = path.parent() {
+                    create_private_dir(parent)?;
+                }
+                write_private_file(path, content)
+            }
+            Self::CreateFile { path, content }
+            | Self::ModifyFile {
+                path, new: content, ..
+            } => {
+                create_parent_dir(path)?;
+                write(path, content)?;
+                Ok(())
+            }
+            Self::GenerateKey {
+                private_key,
+                public_key,
+                options,
+            } => {
+                if let Some(parent) = private_key.parent() {
+                    create_private_dir(parent)?;
+                }
+                options.generate(private_key, public_key)?;
+                share_public_key(public_key)
+            }
+            Self::ReplaceKey {
+                private_key,
+                public_key,
+                new_private_key,
+                new_public_key,
+                backup,
+            } => {
+                let backup_public_key = with_suffix(backup, "pub");
+                let mut moves = vec![];
+                if private_key.exists() {
+                    moves.push((private_key.as_path(), backup.as_path()));
+                }
+                if public_key.exists() {
+                    moves.push((public_key.as_path(), backup_public_key.as_path()));
+                }
+                moves.push((new_private_key, private_key));
+                moves.push((new_public_key, public_key));
+
+                let renamed = rename_all(&moves);
+                if renamed.is_err() {
+                    //> Drop the unused new pair so that the next attempt starts clean
+                    let _ = remove_file(new_private_key);
+                    let _ = remove_file(new_public_key);
+                }
+                renamed
+            }
+            Self::DeleteKey { path } | Self::DeleteFile { path } => Ok(remove_file(path)?),
+            Self::MoveFile { from, to } => Ok(rename(from, to)?),
+            Self::SetPermissions { path, mode } => set_mode(path, *mode),
+            Self::SetConfig {
+                scope, key, new, ..
+            } => scope.set_value(key, new),
+            Self::SetBareConfig { scope, key, .. } => scope.set_bare(key),
+            Self::UnsetConfig { scope, key, .. } => scope.unset(key),
+            Self::UnsetAllConfig { scope, key, .. } => scope.unset_all(key),
+            Self::AddConfig { scope, key, value } => scope.add(key, value),
+            Self::SetRemote { remote, new, .. } => set_remote_url(remote, new),
+        }
+    }
+}
+
+impl fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CreateFile { path, content } => {
+                writeln!(f, "+ create {}", path.display())?;
+                write_diff(f, path, "", content)
+            }
+            Self::ModifyFile { path, old, new } => {
+                writeln!(f, "~ modify {}", path.display())?;
+                write_diff(f, path, old, new)
+            }
+            Self::GenerateKey {
+                private_key,
+                public_key,
+                options,
+            } => writeln!(
+                f,
+                "* generate {} key pair {} (+ {}) labelled '{}' with {}",
+                options.key_type,
+                private_key.display(),
+                public_key.display(),
+                options.comment,
+                options.effective_backend().to_str()
+            ),
+            Self::ReplaceKey {
+                private_key,
+                new_private_key,
+                backup,
+                ..
+            } => writeln!(
+                f,
+                "> replace key pair {} with {}, keeping the old pair as {}",
+                private_key.display(),
+                new_private_key.display(),
+                backup.display()
+            ),
+            Self::DeleteKey { path } => writeln!(f, "- delete key {}", path.display()),
+            Self::DeleteFile { path } => writeln!(f, "- delete {}", path.display()),
+            Self::MoveFile { from, to } => {
+                writeln!(f, "> move {} -> {}", from.display(), to.display())
+            }
+            Self::SetPermissions { path, mode } => {
+                writeln!(f, "~ chmod {:04o} {}", mode, path.display())
+            }
+            Self::SetConfig {
+                scope,
+                key,
+                old,
+                new,
+            } => writeln!(
+                f,
+                "= set {} git config {}: {} -> '{}'",
+                scope,
+                key,
+                old.as_deref()
+                    .map_or("<unset>".to_string(), |old| format!("'{}'", old)),
+                new
+            ),
+            Self::SetBareConfig { scope, key, old } => writeln!(
+                f,
+                "= set {} git config {}: {} -> <no value>",
+                scope,
+                key,
+                old.as_deref()
+                    .map_or("<unset>".to_string(), |old| format!("'{}'", old))
+            ),
+            Self::UnsetConfig { scope, key, old } => {
+                writeln!(f, "- unset {} git config {} (was '{}')", scope, key, old)
+            }
+            Self::UnsetAllConfig { scope, key, old } => writeln!(
+                f,
+                "- unset all {} git config {} (was '{}')",
+                scope,
+                key,
+                old.join("', '")
+            ),
+            Self::AddConfig { scope, key, value } => {
+                writeln!(f, "+ add {} git config {}: '{}'", scope, key, value)
+            }
+            Self::SetRemote { remote, old, new } => {
+                writeln!(f, "= set remote {}: '{}' -> '{}'", remote, old, new)
+            }
+        }
+    }
+}
+
+/// Renames each `from` to its `to` in order; when one fails, the renames done so far are
+/// undone so that every file is back where it was.
+fn rename_all(moves: &[(&Path, &Path)]) -> Result<()> {
+    for (index, (from, to)) in moves.iter().enumerate() {
+        if let Err(error) = rename(from, to) {
+            for (from, to) in moves[..index].iter().rev() {
+                let _ = rename(to, from);
+            }
+            return Err(anyhow!(
+                "Failed to move {} to {}: {}",
+                from.display(),
+                to.display(),
+                error
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// An ordered list of actions, such as the ones `GitInit::execute` would take.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    pub actions: Vec<PlannedAction>,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    pub fn apply(&self) -> Result<()> {
+        self.actions.iter().try_for_each(PlannedAction::apply)
+    }
+
+    /// Applies a plan the user has confirmed as a whole, without asking again for each key
+    /// it overwrites.
+    pub fn apply_confirmed(&self) -> Result<()> {
+        self.actions.iter().try_for_each(|action| match action {
+            PlannedAction::SetConfig {
+                scope, key, new, ..
+            } => scope.replace_value(key, new),
+            action => action.apply(),
+        })
+    }
+
+    /// Prints the plan in dry-run mode, applies it otherwise.
+    pub fn run(&self) -> Result<()> {
+        if is_dry_run() {
+            print!("{}", self);
+            Ok(())
+        } else {
+            self.apply()
+        }
+    }
+}
+
+impl From<Vec<PlannedAction>> for Plan {
+    fn from(actions: Vec<PlannedAction>) -> Self {
+        Self { actions }
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "Nothing to do.");
+        }
+
+        writeln!(f, "Plan ({} actions):", self.actions.len())?;
+        self.actions
+            .iter()
+            .try_for_each(|action| write!(f, "{}", action))
+    }
+}
+
+/// Serializes an optional fix as the one-line description of each of its actions.
+pub(crate) fn serialize_fix<S: Serializer>(
+    fix: &Option<Plan>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let actions: Option<Vec<String>> = fix.as_ref().map(|plan| {
+        plan.actions
+            .iter()
+            .map(|action| action.to_string().trim_end().to_string())
+            .collect()
+    });
+    actions.serialize(serializer)
+}
+
+fn write_diff(f: &mut fmt::Formatter<'_>, path: &Path, old: &str, new: &str) -> fmt::Result {
+    let path = path.display().to_string();
+    let diff = TextDiff::from_lines(old, new);
+    let unified = diff.unified_diff().header(&path, &path).to_string();
+    unified
+        .lines()
+        .try_for_each(|line| writeln!(f, "    {}", line))
+}
+
+fn create_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.exists() {
+            create_dir_all(parent)?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_display_set_config() {
+    let action = PlannedAction::SetConfig {
+        scope: GitConfig::Local,
+        key: "user.email".to_string(),
+        old: None,
+        new: "me@example.com".to_string(),
+    };
+    assert_eq!(
+        action.to_string(),
+        "= set local git config user.email: <unset> -> 'me@example.com'\n"
+    );
+}
+
+#[test]
+fn test_display_modify_file_diff() {
+    let action = PlannedAction::ModifyFile {
+        path: PathBuf::from("/tmp/config"),
+        old: "Host a\n".to_string(),
+        new: "Host a\n\nHost b\n".to_string(),
+    };
+    let rendered = action.to_string();
+    assert!(rendered.starts_with("~ modify /tmp/config\n"));
+    assert!(rendered.contains("    +Host b"));
+}
@@ -0,0 +1,223 @@
+use crate::*;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{create_dir_all, read_to_string, write},
+    path::{Path, PathBuf},
+};
+
+/// A stored `GitInit` identity. Empty fields are derived by `GitInit` when applied.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub email: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub host: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_dir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_config: Option<PathBuf>,
+}
+
+impl Profile {
+    /// Loads the profile into a `GitInit`, ready to be planned or executed.
+    pub fn to_git_init(&self) -> GitInit {
+        GitInit {
+            host: self.host.clone(),
+            name: self.name.clone(),
+            email: self.email.clone(),
+            label: self.label.clone(),
+            alias: self.alias.clone(),
+            ssh_dir: self.ssh_dir.clone(),
+            private_key: self.key.clone(),
+            config_file: self.ssh_config.clone(),
+            ..GitInit::default()
+        }
+    }
+
+    /// Overwrites the fields that are set in `other`, leaving the rest untouched.
+    pub fn merge(&mut self, other: Profile) {
+        macro_rules! set_if_not_empty {
+            ($field:ident) => {
+                if !other.$field.is_empty() {
+                    self.$field = other.$field;
+                }
+            };
+        }
+        set_if_not_empty!(name);
+        set_if_not_empty!(email);
+        set_if_not_empty!(host);
+        set_if_not_empty!(label);
+        self.alias = other.alias.or(self.alias.take());
+        self.key = other.key.or(self.key.take());
+        self.ssh_dir = other.ssh_dir.or(self.ssh_dir.take());
+        self.ssh_config = other.ssh_config.or(self.ssh_config.take());
+    }
+}
+
+impl From<&GitInit> for Profile {
+    fn from(git_init: &GitInit) -> Self {
+        Self {
+            name: git_init.name.clone(),
+            email: git_init.email.clone(),
+            host: git_init.host.clone(),
+            label: git_init.label.clone(),
+            alias: git_init.alias.clone(),
+            key: git_init.private_key.clone(),
+            ssh_dir: git_init.ssh_dir.clone(),
+            ssh_config: git_init.config_file.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let content = toml::to_string_pretty(self).map_err(|_| fmt::Error)?;
+        write!(f, "{}", content)
+    }
+}
+
+/// Every saved profile, kept in `profiles.toml` under the gitsy config directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileStore {
+    #[serde(skip)]
+    pub path: PathBuf,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ProfileStore {
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(get_config_dir()?.join("profiles.toml"))
+    }
+
+    pub fn load() -> Result<Self> {
+        Self::load_from(Self::default_path()?)
+    }
+
+    /// Reads the store at `path`, starting empty if the file does not exist yet.
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut store: Self = if path.exists() {
+            let content = read_to_string(path)?;
+            toml::from_str(&content)
+                .with_context(|| format!("Failed to parse profiles from {}", path.display()))?
+        } else {
+            Self::default()
+        };
+        store.path = path.to_path_buf();
+        Ok(store)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.exists() {
+                create_dir_all(parent)?;
+            }
+        }
+        write(&self.path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Result<&Profile> {
+        self.profiles
+            .get(id)
+            .ok_or_else(|| anyhow!("No profile named '{}'", id))
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Result<&mut Profile> {
+        self.profiles
+            .get_mut(id)
+            .ok_or_else(|| anyhow!("No profile named '{}'", id))
+    }
+
+    pub fn add(&mut self, id: &str, profile: Profile) -> Result<()> {
+        validate_profile_id(id)?;
+        if self.profiles.contains_key(id) {
+            return Err(anyhow!("Profile '{}' already exists", id));
+        }
+        self.profiles.insert(id.to_string(), profile);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<Profile> {
+        self.profiles
+            .remove(id)
+            .ok_or_else(|| anyhow!("No profile named '{}'", id))
+    }
+}
+
+/// Fails unless `id` can name a file of its own: letters, digits, `.`, `_` and `-`, not
+/// starting with a dot.
+pub fn validate_profile_id(id: &str) -> Result<()> {
+    let valid = !id.is_empty()
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Invalid profile name '{}': use letters, digits, '.', '_' and '-', not starting with '.'",
+            id
+        ))
+    }
+}
+
+#[test]
+fn test_profile_store_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("gitsy").join("profiles.toml");
+
+    let mut store = ProfileStore::load_from(&path).unwrap();
+    assert!(store.profiles.is_empty());
+
+    let work = Profile {
+        name: "Jane Doe".to_string(),
+        email: "jane@work.example".to_string(),
+        host: "github.com".to_string(),
+        ..Profile::default()
+    };
+    store.add("work", work.clone()).unwrap();
+    assert!(store.add("work", Profile::default()).is_err());
+    for id in ["../../foo", "a/b", ".hidden", "", "my work"] {
+        assert!(store.add(id, Profile::default()).is_err(), "{}", id);
+    }
+    store.add("work-2.old_1", Profile::default()).unwrap();
+    store.remove("work-2.old_1").unwrap();
+    store.save().unwrap();
+
+    let mut store = ProfileStore::load_from(&path).unwrap();
+    assert_eq!(store.get("work").unwrap(), &work);
+    assert_eq!(store.get("work").unwrap().to_git_init().email, work.email);
+
+    store.remove("work").unwrap();
+    assert!(store.get("work").is_err());
+}
+
+#[test]
+fn test_profile_merge() {
+    let mut profile = Profile {
+        name: "jane".to_string(),
+        email: "jane@old.example".to_string(),
+        ..Profile::default()
+    };
+    profile.merge(Profile {
+        email: "jane@new.example".to_string(),
+        ..Profile::default()
+    });
+    assert_eq!(profile.name, "jane");
+    assert_eq!(profile.email, "jane@new.example");
+}
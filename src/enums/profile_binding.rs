@@ -0,0 +1,225 @@
+use crate::*;
+use anyhow::{anyhow, Result};
+use std::{
+    env::current_dir,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// A `[includeIf "gitdir:..."]` entry in the global config that points at a profile's include file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileBinding {
+    pub directory: String,
+    pub profile: String,
+    pub path: PathBuf,
+}
+
+impl ProfileBinding {
+    /// Directory holding the generated per-profile git config files.
+    pub fn profiles_dir() -> Result<PathBuf> {
+        Ok(get_config_dir()?.join("profiles"))
+    }
+
+    pub fn profile_file(id: &str) -> Result<PathBuf> {
+        validate_profile_id(id)?;
+        Ok(Self::profiles_dir()?.join(format!("{}.gitconfig", id)))
+    }
+
+    /// Lists every binding in the global config that targets a gitsy profile file.
+    pub fn list() -> Result<Vec<Self>> {
+        let entries = GitConfig::Global.get_regexp(r"^includeif\..*\.path$")?;
+        Ok(parse_bindings(&entries, &Self::profiles_dir()?))
+    }
+
+    /// Writes the profile's include file and maps `directory` to it, skipping what is already in place.
+    pub fn plan_bind(id: &str, profile: &Profile, directory: &str) -> Result<Plan> {
+        let directory = normalize_gitdir(directory)?;
+        let mut actions = plan_profile_file(id, profile)?;
+
+        let key = include_key(&directory);
+        let path = Self::profile_file(id)?.display().to_string();
+        let old = GitConfig::Global.get_value(&key)?;
+        if old.as_deref() != Some(path.as_str()) {
+            actions.push(PlannedAction::SetConfig {
+                scope: GitConfig::Global,
+                key,
+                old,
+                new: path,
+            });
+        }
+
+        Ok(Plan::from(actions))
+    }
+
+    pub fn plan_unbind(directory: &str) -> Result<Plan> {
+        let directory = normalize_gitdir(directory)?;
+        let key = include_key(&directory);
+
+        Ok(Plan::from(
+            GitConfig::Global
+                .get_value(&key)?
+                .map(|old| PlannedAction::UnsetConfig {
+                    scope: GitConfig::Global,
+                    key,
+                    old,
+                })
+                .into_iter()
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    /// Regenerates the include file of a bound profile after it was edited.
+    pub fn plan_sync(id: &str, profile: &Profile) -> Result<Plan> {
+        if Self::list()?.iter().any(|binding| binding.profile == id) {
+            Ok(Plan::from(plan_profile_file(id, profile)?))
+        } else {
+            Ok(Plan::default())
+        }
+    }
+
+    /// Drops every binding of a profile along with its include file.
+    pub fn plan_remove_all(id: &str) -> Result<Plan> {
+        let mut actions: Vec<PlannedAction> = Self::list()?
+            .into_iter()
+            .filter(|binding| binding.profile == id)
+            .map(|binding| PlannedAction::UnsetConfig {
+                scope: GitConfig::Global,
+                key: include_key(&binding.directory),
+                old: binding.path.display().to_string(),
+            })
+            .collect();
+
+        //> A profile stored before ids were validated may have no file of its own to delete
+        if let Some(path) = Self::profile_file(id).ok().filter(|path| path.exists()) {
+            actions.push(PlannedAction::DeleteFile { path });
+        }
+
+        Ok(Plan::from(actions))
+    }
+}
+
+impl fmt::Display for ProfileBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<32} {}", self.directory, self.profile)
+    }
+}
+
+fn include_key(directory: &str) -> String {
+    format!("includeIf.gitdir:{}.path", directory)
+}
+
+/// Makes `directory` absolute (or `~/`-relative) and adds the trailing slash git needs to match everything below it.
+fn normalize_gitdir(directory: &str) -> Result<String> {
+    if directory.is_empty() {
+        return Err(anyhow!("The directory to bind must not be empty"));
+    }
+
+    let mut directory = if directory.starts_with('~') || Path::new(directory).is_absolute() {
+        directory.to_string()
+    } else {
+        current_dir()?.join(directory).display().to_string()
+    };
+
+    if !directory.ends_with('/') {
+        directory.push('/');
+    }
+    Ok(directory)
+}
+
+fn plan_profile_file(id: &str, profile: &Profile) -> Result<Vec<PlannedAction>> {
+    plan_profile_values(&GitConfig::File(ProfileBinding::profile_file(id)?), profile)
+}
+
+/// Sets the identity keys of a profile's include file through the same API as any other config.
+fn plan_profile_values(scope: &GitConfig, profile: &Profile) -> Result<Vec<PlannedAction>> {
+    let mut actions = vec![];
+    for (key, value) in [("user.name", &profile.name), ("user.email", &profile.email)] {
+        if value.is_empty() {
+            continue;
+        }
+        let old = scope.get_value(key)?;
+        if old.as_deref() != Some(value.as_str()) {
+            actions.push(PlannedAction::SetConfig {
+                scope: scope.clone(),
+                key: key.to_string(),
+                old,
+                new: value.clone(),
+            });
+        }
+    }
+    Ok(actions)
+}
+
+fn parse_bindings(entries: &[(String, String)], profiles_dir: &Path) -> Vec<ProfileBinding> {
+    entries
+        .iter()
+        .filter_map(|(key, value)| {
+            let directory = key
+                .strip_prefix("includeif.gitdir:")?
+                .strip_suffix(".path")?;
+            let path = PathBuf::from(value);
+            if path.parent()? != profiles_dir {
+                return None;
+            }
+
+            Some(ProfileBinding {
+                directory: directory.to_string(),
+                profile: path.file_stem()?.to_string_lossy().to_string(),
+                path,
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_normalize_gitdir() {
+    assert_eq!(normalize_gitdir("~/work").unwrap(), "~/work/");
+    assert_eq!(normalize_gitdir("/srv/oss/").unwrap(), "/srv/oss/");
+    assert!(normalize_gitdir("client").unwrap().ends_with("/client/"));
+}
+
+#[test]
+fn test_parse_bindings() {
+    let profiles_dir = Path::new("/home/jane/.config/gitsy/profiles");
+    let entries = vec![
+        (
+            "includeif.gitdir:~/work/.path".to_string(),
+            "/home/jane/.config/gitsy/profiles/work.gitconfig".to_string(),
+        ),
+        (
+            "includeif.gitdir:~/misc/.path".to_string(),
+            "/home/jane/other.gitconfig".to_string(),
+        ),
+    ];
+
+    assert_eq!(
+        parse_bindings(&entries, profiles_dir),
+        vec![ProfileBinding {
+            directory: "~/work/".to_string(),
+            profile: "work".to_string(),
+            path: profiles_dir.join("work.gitconfig"),
+        }]
+    );
+}
+
+#[test]
+fn test_plan_profile_values() {
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("work.gitconfig");
+    std::fs::write(&path, "[user]\n\tname = Jane Doe\n").unwrap();
+
+    let profile = Profile {
+        name: "Jane Doe".to_string(),
+        email: "jane@work.example".to_string(),
+        ..Profile::default()
+    };
+    assert_eq!(
+        plan_profile_values(&GitConfig::File(path.clone()), &profile).unwrap(),
+        vec![PlannedAction::SetConfig {
+            scope: GitConfig::File(path),
+            key: "user.email".to_string(),
+            old: None,
+            new: "jane@work.example".to_string(),
+        }]
+    );
+}
@@ -1,4 +1,44 @@
+use crate::*;
+use anyhow::{anyhow, Result};
 use std::io::{stdin, stdout, Write};
+use std::str::FromStr;
+
+/// How prompts are answered: by the user, or automatically for scripted runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnswerMode {
+    Interactive,
+    Yes,
+    No,
+    Default,
+}
+
+impl AnswerMode {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Interactive => "interactive",
+            Self::Yes => "yes",
+            Self::No => "no",
+            Self::Default => "default",
+        }
+    }
+}
+
+impl FromStr for AnswerMode {
+    type Err = anyhow::Error;
+
+    fn from_str(mode: &str) -> Result<Self> {
+        match mode.to_lowercase().as_str() {
+            "interactive" | "ask" => Ok(Self::Interactive),
+            "yes" | "y" => Ok(Self::Yes),
+            "no" | "n" => Ok(Self::No),
+            "default" | "defaults" => Ok(Self::Default),
+            _ => Err(anyhow!(
+                "Unknown answer mode '{}' (expected yes, no, default or interactive)",
+                mode
+            )),
+        }
+    }
+}
 
 pub struct PromptWithOptions<'a> {
     pub message: &'a str,
@@ -84,10 +124,41 @@ impl<'a> PromptWithOptions<'a> {
             || selection.to_lowercase() == self.default.1.to_lowercase()
     }
     pub fn prompt(&self) -> PromptResult {
+        self.prompt_as(answer_mode())
+    }
+
+    /// Prompts the user, or picks and logs the answer `mode` dictates without reading stdin.
+    pub fn prompt_as(&self, mode: AnswerMode) -> PromptResult {
         let default_option_value = self
             .get_option_value(self.default.0)
             .unwrap_or_else(|| self.default.1.to_string());
 
+        if mode != AnswerMode::Interactive {
+            let wanted = match mode {
+                AnswerMode::Yes => Some(("y", "yes")),
+                AnswerMode::No => Some(("n", "no")),
+                _ => None,
+            };
+            let (key, value) = wanted
+                .and_then(|(short, long)| {
+                    self.options.iter().find(|(key, value)| {
+                        key.eq_ignore_ascii_case(short) || value.eq_ignore_ascii_case(long)
+                    })
+                })
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .unwrap_or((self.default_option_key(), default_option_value));
+
+            //> Log the answer that was picked on the user's behalf
+            println!(
+                "{}\n=> {}: {} [{}]",
+                self.message,
+                key,
+                value,
+                mode.to_str()
+            );
+            return PromptResult::Success(value);
+        }
+
         //> Print available options
         self.print();
 
@@ -113,14 +184,32 @@ impl<'a> PromptWithOptions<'a> {
         });
 
         // Return the selected option
-        if option.is_none() {
-            PromptResult::Error(format!(
+        match option {
+            Some((_, value)) => PromptResult::Success(value.to_string()),
+            None => PromptResult::Error(format!(
                 "Invalid option selected. Defaulting to: {}",
                 default_option_value
-            ))
-        } else {
-            let selected_option_value = option.unwrap().1.to_string();
-            PromptResult::Success(selected_option_value)
+            )),
+        }
+    }
+}
+
+#[test]
+fn test_prompt_as_non_interactive() {
+    let prompt_options = PromptWithOptions {
+        message: "Should we proceed?",
+        options: vec![("y", "Yes"), ("n", "No"), ("a", "Always")],
+        default: ("a", "Always"),
+    };
+
+    for (mode, expected) in [
+        (AnswerMode::Yes, "Yes"),
+        (AnswerMode::No, "No"),
+        (AnswerMode::Default, "Always"),
+    ] {
+        match prompt_options.prompt_as(mode) {
+            PromptResult::Success(selection) => assert_eq!(selection, expected),
+            PromptResult::Error(error) => panic!("{}", error),
         }
     }
 }
@@ -0,0 +1,337 @@
+use anyhow::{anyhow, Result};
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteScheme {
+    /// `[user@]host:path`
+    Scp,
+    Ssh,
+    Git,
+    Http,
+    Https,
+    File,
+    /// A path on this machine, such as `/srv/git/app.git` or `../app.git`
+    Local,
+}
+
+impl RemoteScheme {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::Scp => "scp",
+            Self::Ssh => "ssh",
+            Self::Git => "git",
+            Self::Http => "http",
+            Self::Https => "https",
+            Self::File => "file",
+            Self::Local => "local",
+        }
+    }
+
+    pub fn is_ssh(&self) -> bool {
+        matches!(self, Self::Scp | Self::Ssh)
+    }
+}
+
+/// A git remote URL split into its parts, rendering back to the same form it was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteUrl {
+    pub scheme: RemoteScheme,
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    /// Repository path as written; for URL schemes other than `file`, without the `/` after the host.
+    pub path: String,
+}
+
+impl RemoteUrl {
+    pub fn parse(url: &str) -> Result<Self> {
+        let url = url.trim();
+        let invalid = || anyhow!("Unrecognized git remote URL: '{}'", url);
+
+        if let Some((scheme, rest)) = url.split_once("://") {
+            let scheme = match scheme.to_lowercase().as_str() {
+                "ssh" | "git+ssh" | "ssh+git" => RemoteScheme::Ssh,
+                "git" => RemoteScheme::Git,
+                "http" => RemoteScheme::Http,
+                "https" => RemoteScheme::Https,
+                "file" => RemoteScheme::File,
+                _ => return Err(invalid()),
+            };
+
+            if scheme == RemoteScheme::File {
+                return Ok(Self::file(rest));
+            }
+
+            let (authority, path) = rest.split_once('/').ok_or_else(invalid)?;
+            let (user, host_port) = match authority.rsplit_once('@') {
+                Some((user, host_port)) => (Some(user.to_string()), host_port),
+                None => (None, authority),
+            };
+            let (host, port) = match host_port.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port.parse().map_err(|_| invalid())?)),
+                None => (host_port, None),
+            };
+            if host.is_empty() || path.is_empty() {
+                return Err(invalid());
+            }
+
+            return Ok(Self {
+                scheme,
+                user,
+                host: host.to_string(),
+                port,
+                path: path.to_string(),
+            });
+        }
+
+        //> scp-style needs a colon before the first slash; anything else is a local path
+        match url.split_once(':') {
+            Some((authority, path)) if !authority.contains('/') && !path.is_empty() => {
+                let (user, host) = match authority.rsplit_once('@') {
+                    Some((user, host)) => (Some(user.to_string()), host),
+                    None => (None, authority),
+                };
+                if host.is_empty() {
+                    return Err(invalid());
+                }
+
+                Ok(Self {
+                    scheme: RemoteScheme::Scp,
+                    user,
+                    host: host.to_string(),
+                    port: None,
+                    path: path.to_string(),
+                })
+            }
+            _ if !url.is_empty() => Ok(Self::local(url)),
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            scheme: RemoteScheme::File,
+            user: None,
+            host: String::new(),
+            port: None,
+            path: path.into(),
+        }
+    }
+
+    pub fn local(path: impl Into<String>) -> Self {
+        Self {
+            scheme: RemoteScheme::Local,
+            ..Self::file(path)
+        }
+    }
+
+    /// `git@host:path`, the form providers print for SSH clones.
+    pub fn scp(host: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            scheme: RemoteScheme::Scp,
+            user: Some("git".to_string()),
+            host: host.into(),
+            port: None,
+            path: path.into(),
+        }
+    }
+
+    /// Everything before the repository name, e.g. `acme` or `group/subgroup`.
+    pub fn owner(&self) -> &str {
+        let path = self.path.trim_matches('/');
+        path.rsplit_once('/').map_or("", |(owner, _)| owner)
+    }
+
+    /// The repository name without a trailing `.git`.
+    pub fn repo(&self) -> &str {
+        let path = self.path.trim_matches('/');
+        let name = path.rsplit_once('/').map_or(path, |(_, name)| name);
+        name.strip_suffix(".git").unwrap_or(name)
+    }
+
+    /// The same repository over SSH, as `git@host:owner/repo.git` (or `ssh://` to keep a custom port).
+    pub fn to_ssh(&self) -> Result<Self> {
+        match self.scheme {
+            RemoteScheme::Scp | RemoteScheme::Ssh => Ok(self.clone()),
+            RemoteScheme::File | RemoteScheme::Local => {
+                Err(anyhow!("A local remote has no SSH form: {}", self))
+            }
+            _ => Ok(Self::scp(&self.host, self.git_path())),
+        }
+    }
+
+    /// The same repository over HTTPS, as `https://host/owner/repo.git`.
+    pub fn to_https(&self) -> Result<Self> {
+        match self.scheme {
+            RemoteScheme::Https => Ok(self.clone()),
+            RemoteScheme::File | RemoteScheme::Local => {
+                Err(anyhow!("A local remote has no HTTPS form: {}", self))
+            }
+            _ => Ok(Self {
+                scheme: RemoteScheme::Https,
+                user: None,
+                host: self.host.clone(),
+                port: None,
+                path: self.git_path(),
+            }),
+        }
+    }
+
+    pub fn with_host(mut self, host: impl Into<String>) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    fn git_path(&self) -> String {
+        let path = self.path.trim_matches('/');
+        if path.ends_with(".git") {
+            path.to_string()
+        } else {
+            format!("{}.git", path)
+        }
+    }
+}
+
+impl FromStr for RemoteUrl {
+    type Err = anyhow::Error;
+
+    fn from_str(url: &str) -> Result<Self> {
+        Self::parse(url)
+    }
+}
+
+impl fmt::Display for RemoteUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let user = self
+            .user
+            .as_ref()
+            .map_or(String::new(), |user| format!("{}@", user));
+        let port = self.port.map_or(String::new(), |port| format!(":{}", port));
+
+        match self.scheme {
+            RemoteScheme::Scp => write!(f, "{}{}:{}", user, self.host, self.path),
+            RemoteScheme::File => write!(f, "file://{}", self.path),
+            RemoteScheme::Local => write!(f, "{}", self.path),
+            scheme => write!(
+                f,
+                "{}://{}{}{}/{}",
+                scheme.to_str(),
+                user,
+                self.host,
+                port,
+                self.path
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_parse_remote_urls() {
+    let scp = RemoteUrl::parse("git@github.com:acme/app.git").unwrap();
+    assert_eq!(scp.scheme, RemoteScheme::Scp);
+    assert_eq!(
+        (scp.host.as_str(), scp.owner(), scp.repo()),
+        ("github.com", "acme", "app")
+    );
+
+    let ssh = RemoteUrl::parse("ssh://git@gitlab.example.com:2222/group/sub/app.git").unwrap();
+    assert_eq!(ssh.port, Some(2222));
+    assert_eq!((ssh.owner(), ssh.repo()), ("group/sub", "app"));
+
+    let https = RemoteUrl::parse("https://github.com/acme/app").unwrap();
+    assert_eq!((https.host.as_str(), https.repo()), ("github.com", "app"));
+
+    let file = RemoteUrl::parse("file:///srv/git/app.git").unwrap();
+    assert_eq!(
+        (file.path.as_str(), file.repo()),
+        ("/srv/git/app.git", "app")
+    );
+
+    let absolute = RemoteUrl::parse("git@example.com:/srv/git/app.git").unwrap();
+    assert_eq!(absolute.path, "/srv/git/app.git");
+    assert_eq!((absolute.owner(), absolute.repo()), ("srv/git", "app"));
+
+    let local = RemoteUrl::parse("/srv/git/app.git").unwrap();
+    assert_eq!((local.scheme, local.repo()), (RemoteScheme::Local, "app"));
+    assert!(local.to_ssh().is_err());
+
+    for url in [
+        "git@github.com:acme/app.git",
+        "git@example.com:/srv/git/app.git",
+        "/srv/git/app.git",
+        "../app.git",
+        "ssh://git@gitlab.example.com:2222/group/sub/app.git",
+        "https://github.com/acme/app",
+        "file:///srv/git/app.git",
+    ] {
+        assert_eq!(RemoteUrl::parse(url).unwrap().to_string(), url);
+    }
+    assert!(RemoteUrl::parse("ftp://example.com/app.git").is_err());
+}
+
+#[test]
+fn test_convert_remote_urls() {
+    let https = RemoteUrl::parse("https://github.com/acme/app").unwrap();
+    assert_eq!(
+        https.to_ssh().unwrap().to_string(),
+        "git@github.com:acme/app.git"
+    );
+
+    let scp = RemoteUrl::parse("git@github.com:acme/app.git").unwrap();
+    assert_eq!(
+        scp.to_https().unwrap().to_string(),
+        "https://github.com/acme/app.git"
+    );
+    assert_eq!(
+        scp.with_host("github.com-work").to_string(),
+        "git@github.com-work:acme/app.git"
+    );
+}
+
+#[test]
+fn test_rewrite_remote_of_local_clone() {
+    use crate::{get_remote_urls_in, set_remote_url_in, GitInit, PlannedAction};
+    use std::process::Command;
+
+    let temp = tempfile::tempdir().unwrap();
+    let bare = temp.path().join("app.git");
+    let clone = temp.path().join("clone");
+    for args in [
+        vec!["init", "--quiet", "--bare", bare.to_str().unwrap()],
+        vec![
+            "clone",
+            "--quiet",
+            bare.to_str().unwrap(),
+            clone.to_str().unwrap(),
+        ],
+    ] {
+        let output = Command::new("git").args(&args).output().unwrap();
+        assert!(output.status.success(), "git {:?}", args);
+    }
+
+    //> The clone's origin is the bare repository's path, as written
+    let origin = || {
+        let (_, url) = get_remote_urls_in(&clone).unwrap().pop().unwrap();
+        RemoteUrl::parse(&url).unwrap()
+    };
+    assert_eq!(origin(), RemoteUrl::local(bare.display().to_string()));
+    assert_eq!(origin().to_string(), bare.display().to_string());
+
+    //> A local remote is left alone, an SSH one on the host moves to the alias
+    let mut git_init = GitInit::new()
+        .with_name("jane")
+        .with_host("github.com")
+        .with_alias("work")
+        .with_ssh_dir(temp.path().join(".ssh"));
+    assert!(git_init.plan_remotes_in(&clone).unwrap().is_empty());
+
+    let ssh = RemoteUrl::scp("github.com", "acme/app.git");
+    set_remote_url_in(&clone, "origin", &ssh.to_string()).unwrap();
+    for action in git_init.plan_remotes_in(&clone).unwrap() {
+        let PlannedAction::SetRemote { remote, new, .. } = action else {
+            panic!("unexpected {:?}", action);
+        };
+        set_remote_url_in(&clone, &remote, &new).unwrap();
+    }
+    assert_eq!(origin(), ssh.with_host("github.com-work"));
+}
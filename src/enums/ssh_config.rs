@@ -0,0 +1,587 @@
+use crate::*;
+use anyhow::{Context, Result};
+use std::{fmt, fs::read_to_string, path::Path};
+
+/// Keywords whose values accumulate instead of the first one winning.
+const CUMULATIVE_KEYWORDS: [&str; 7] = [
+    "identityfile",
+    "certificatefile",
+    "localforward",
+    "remoteforward",
+    "dynamicforward",
+    "sendenv",
+    "setenv",
+];
+
+/// One physical line of an SSH config file, kept verbatim so comments and spacing survive a rewrite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshConfigLine {
+    pub raw: String,
+}
+
+impl SshConfigLine {
+    pub fn new(raw: impl Into<String>) -> Self {
+        Self { raw: raw.into() }
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.raw.trim().is_empty()
+    }
+
+    pub fn is_comment(&self) -> bool {
+        self.raw.trim_start().starts_with('#')
+    }
+
+    /// Splits `Keyword value` or `Keyword=value` into its parts, without the quotes around a
+    /// single quoted value; `None` for blanks and comments.
+    pub fn directive(&self) -> Option<(&str, &str)> {
+        if self.is_blank() || self.is_comment() {
+            return None;
+        }
+
+        let line = self.raw.trim();
+        let end = line
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(line.len());
+        let (key, rest) = line.split_at(end);
+        let rest = rest.trim_start();
+        let value = rest.strip_prefix('=').unwrap_or(rest).trim();
+        let unquoted = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .filter(|value| !value.contains('"'));
+        Some((key, unquoted.unwrap_or(value)))
+    }
+
+    fn indent(&self) -> &str {
+        &self.raw[..self.raw.len() - self.raw.trim_start().len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SshBlockKind {
+    Host,
+    Match,
+}
+
+/// A `Host` or `Match` line together with every line up to the next block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshBlock {
+    pub kind: SshBlockKind,
+    pub header: SshConfigLine,
+    pub lines: Vec<SshConfigLine>,
+}
+
+impl SshBlock {
+    pub fn new_host(patterns: &str) -> Self {
+        Self {
+            kind: SshBlockKind::Host,
+            header: SshConfigLine::new(format!("Host {}", patterns)),
+            lines: vec![],
+        }
+    }
+
+    /// The host patterns of a `Host` block, or the criteria of a `Match` block.
+    pub fn patterns(&self) -> Vec<String> {
+        self.header
+            .directive()
+            .map(|(_, value)| {
+                value
+                    .split_whitespace()
+                    .map(|pattern| pattern.trim_matches('"').to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn has_pattern(&self, pattern: &str) -> bool {
+        self.kind == SshBlockKind::Host
+            && self
+                .patterns()
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(pattern))
+    }
+
+    /// Whether ssh would apply this block to `host`, honouring `*`, `?` and `!` patterns.
+    pub fn matches(&self, host: &str) -> bool {
+        if self.kind != SshBlockKind::Host {
+            return false;
+        }
+
+        let patterns = self.patterns();
+        let negated = patterns
+            .iter()
+            .filter_map(|pattern| pattern.strip_prefix('!'));
+        let positive = patterns.iter().filter(|pattern| !pattern.starts_with('!'));
+
+        positive
+            .clone()
+            .any(|pattern| wildcard_match(pattern, host))
+            && !negated.clone().any(|pattern| wildcard_match(pattern, host))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.directives()
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn directives(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(SshConfigLine::directive)
+    }
+
+    /// Sets `key` in place, keeping its position and indentation; new keys go after the last directive.
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        let indent = self.indent();
+        let quoted = quote_value(value);
+        let line = SshConfigLine::new(format!("{}{} {}", indent, key, quoted));
+        let positions: Vec<usize> = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| {
+                line.directive()
+                    .is_some_and(|(candidate, _)| candidate.eq_ignore_ascii_case(key))
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        match positions.split_first() {
+            Some((&first, rest)) => {
+                let unchanged = rest.is_empty()
+                    && self.lines[first]
+                        .directive()
+                        .is_some_and(|(_, current)| current == value);
+                if !unchanged {
+                    self.lines[first] = SshConfigLine::new(format!(
+                        "{}{} {}",
+                        self.lines[first].indent(),
+                        key,
+                        quoted
+                    ));
+                    //> Drop repeated lines so the managed value is the only one
+                    rest.iter().rev().for_each(|&index| {
+                        self.lines.remove(index);
+                    });
+                }
+                !unchanged
+            }
+            None => {
+                let position = self
+                    .lines
+                    .iter()
+                    .rposition(|line| line.directive().is_some())
+                    .map_or(0, |index| index + 1);
+                self.lines.insert(position, line);
+                true
+            }
+        }
+    }
+
+    /// Removes every `key` line. Returns whether there was any.
+    pub fn unset(&mut self, key: &str) -> bool {
+        let count = self.lines.len();
+        self.lines.retain(|line| {
+            !line
+                .directive()
+                .is_some_and(|(candidate, _)| candidate.eq_ignore_ascii_case(key))
+        });
+        self.lines.len() != count
+    }
+
+    fn indent(&self) -> String {
+        self.lines
+            .iter()
+            .find(|line| line.directive().is_some())
+            .map_or_else(|| "\t".to_string(), |line| line.indent().to_string())
+    }
+}
+
+/// `value` in double quotes when it has whitespace, which ssh would split into arguments.
+fn quote_value(value: &str) -> String {
+    if value.contains(char::is_whitespace) && !value.starts_with('"') {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// A problem ssh would silently resolve by ignoring part of the config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SshConflict {
+    /// Two `Host` blocks list the same pattern; only the first one's values are used.
+    Duplicate {
+        pattern: String,
+        first_line: usize,
+        line: usize,
+    },
+    /// An earlier wildcard block already sets `key` for `host`, so the later value is ignored.
+    Shadowed {
+        host: String,
+        line: usize,
+        key: String,
+        by: String,
+        by_line: usize,
+    },
+}
+
+impl fmt::Display for SshConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate {
+                pattern,
+                first_line,
+                line,
+            } => write!(
+                f,
+                "line {}: Host '{}' is already defined on line {}",
+                line, pattern, first_line
+            ),
+            Self::Shadowed {
+                host,
+                line,
+                key,
+                by,
+                by_line,
+            } => write!(
+                f,
+                "line {}: {} for Host '{}' is shadowed by Host '{}' on line {}",
+                line, key, host, by, by_line
+            ),
+        }
+    }
+}
+
+/// A parsed SSH client config that round-trips untouched lines exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SshConfig {
+    /// Lines before the first block, which apply to every host.
+    pub preamble: Vec<SshConfigLine>,
+    pub blocks: Vec<SshBlock>,
+    trailing_newline: bool,
+}
+
+impl SshConfig {
+    pub fn parse(content: &str) -> Self {
+        let mut config = Self {
+            trailing_newline: content.ends_with('\n'),
+            ..Self::default()
+        };
+
+        for raw in content.lines() {
+            let line = SshConfigLine::new(raw);
+            let kind = match line.directive() {
+                Some((key, _)) if key.eq_ignore_ascii_case("host") => Some(SshBlockKind::Host),
+                Some((key, _)) if key.eq_ignore_ascii_case("match") => Some(SshBlockKind::Match),
+                _ => None,
+            };
+
+            match (kind, config.blocks.last_mut()) {
+                (Some(kind), _) => config.blocks.push(SshBlock {
+                    kind,
+                    header: line,
+                    lines: vec![],
+                }),
+                (None, Some(block)) => block.lines.push(line),
+                (None, None) => config.preamble.push(line),
+            }
+        }
+
+        config
+    }
+
+    /// Reads the config at `path`, starting empty if it does not exist yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = read_to_string(path)
+            .with_context(|| format!("Failed to read the SSH config {}", path.display()))?;
+        Ok(Self::parse(&content))
+    }
+
+    /// The action that writes this config to `path`, or `None` if the file already matches.
+    pub fn plan_save<P: AsRef<Path>>(&self, path: P) -> Result<Option<PlannedAction>> {
+        PlannedAction::write_file(path.as_ref(), self.to_string())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.preamble.is_empty() && self.blocks.is_empty()
+    }
+
+    pub fn find_host(&self, pattern: &str) -> Option<&SshBlock> {
+        self.blocks.iter().find(|block| block.has_pattern(pattern))
+    }
+
+    pub fn find_host_mut(&mut self, pattern: &str) -> Option<&mut SshBlock> {
+        self.blocks
+            .iter_mut()
+            .find(|block| block.has_pattern(pattern))
+    }
+
+    /// Updates the first `Host` block listing `pattern` in place, or appends a new one.
+    /// Returns whether anything changed.
+    pub fn upsert_host(&mut self, pattern: &str, directives: &[(&str, &str)]) -> bool {
+        if let Some(block) = self.find_host_mut(pattern) {
+            return directives.iter().fold(false, |changed, (key, value)| {
+                block.set(key, value) || changed
+            });
+        }
+
+        let mut block = SshBlock::new_host(pattern);
+        directives.iter().for_each(|(key, value)| {
+            block.set(key, value);
+        });
+
+        //> Separate the new block from the previous content with a blank line
+        let previous = match self.blocks.last_mut() {
+            Some(last) => Some(&mut last.lines),
+            None if !self.preamble.is_empty() => Some(&mut self.preamble),
+            None => None,
+        };
+        if let Some(lines) = previous {
+            if lines.last().is_some_and(|line| !line.is_blank()) {
+                lines.push(SshConfigLine::new(""));
+            }
+        }
+
+        if self.is_empty() {
+            self.trailing_newline = true;
+        }
+        self.blocks.push(block);
+        true
+    }
+
+    /// Removes every `Host` block that lists exactly `pattern`. Returns whether any was found.
+    pub fn remove_host(&mut self, pattern: &str) -> bool {
+        let count = self.blocks.len();
+        self.blocks.retain(|block| !block.has_pattern(pattern));
+        let removed = self.blocks.len() != count;
+
+        //> Drop the separator that preceded a removed final block
+        if removed {
+            if let Some(last) = self.blocks.last_mut() {
+                while last.lines.last().is_some_and(SshConfigLine::is_blank) {
+                    last.lines.pop();
+                }
+            }
+        }
+        removed
+    }
+
+    /// Detects duplicate `Host` patterns and values shadowed by earlier wildcard blocks.
+    pub fn conflicts(&self) -> Vec<SshConflict> {
+        let hosts: Vec<(usize, &SshBlock)> = self
+            .block_lines()
+            .into_iter()
+            .zip(&self.blocks)
+            .filter(|(_, block)| block.kind == SshBlockKind::Host)
+            .collect();
+        let mut conflicts = vec![];
+
+        for (index, &(line, block)) in hosts.iter().enumerate() {
+            for pattern in block.patterns() {
+                if pattern.starts_with('!') {
+                    continue;
+                }
+
+                //> The first earlier block defining the very same pattern
+                let duplicate = hosts[..index]
+                    .iter()
+                    .find(|(_, earlier)| earlier.has_pattern(&pattern));
+                if let Some(&(first_line, _)) = duplicate {
+                    conflicts.push(SshConflict::Duplicate {
+                        pattern: pattern.clone(),
+                        first_line,
+                        line,
+                    });
+                    continue;
+                }
+
+                if pattern.contains(['*', '?']) {
+                    continue;
+                }
+
+                for &(by_line, earlier) in &hosts[..index] {
+                    if !earlier.matches(&pattern) {
+                        continue;
+                    }
+                    for (key, _) in block.directives() {
+                        let cumulative = CUMULATIVE_KEYWORDS.contains(&key.to_lowercase().as_str());
+                        if !cumulative && earlier.get(key).is_some() {
+                            conflicts.push(SshConflict::Shadowed {
+                                host: pattern.clone(),
+                                line,
+                                key: key.to_string(),
+                                by: earlier.patterns().join(" "),
+                                by_line,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    /// 1-based line number of every block header.
+    fn block_lines(&self) -> Vec<usize> {
+        let mut line = self.preamble.len() + 1;
+        self.blocks
+            .iter()
+            .map(|block| {
+                let header_line = line;
+                line += 1 + block.lines.len();
+                header_line
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for SshConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<&str> = self
+            .preamble
+            .iter()
+            .chain(
+                self.blocks
+                    .iter()
+                    .flat_map(|block| std::iter::once(&block.header).chain(&block.lines)),
+            )
+            .map(|line| line.raw.as_str())
+            .collect();
+
+        write!(f, "{}", lines.join("\n"))?;
+        if self.trailing_newline && !lines.is_empty() {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Matches ssh's host patterns, where `*` is any run of characters and `?` any single one.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[test]
+fn test_round_trip_keeps_comments_and_order() {
+    let content = "# global\nAddKeysToAgent yes\n\nHost github.com\n    User git # not a comment\n\n# work\nMatch host *.corp exec true\n  ProxyJump bastion\nHost=gitlab.com\n\tUser git";
+    assert_eq!(SshConfig::parse(content).to_string(), content);
+}
+
+#[test]
+fn test_quoted_values_round_trip() {
+    let mut config =
+        SshConfig::parse("Host a\n\tIdentityFile \"~/.ssh/my key\"\n\tSendEnv \"A\" \"B\"\n");
+    let block = config.find_host_mut("a").unwrap();
+    assert_eq!(block.get("IdentityFile"), Some("~/.ssh/my key"));
+    assert_eq!(block.get("SendEnv"), Some("\"A\" \"B\""));
+    assert!(!block.set("IdentityFile", "~/.ssh/my key"));
+
+    assert!(block.set("IdentityFile", "/keys/new key"));
+    let content = config.to_string();
+    assert_eq!(
+        content,
+        "Host a\n\tIdentityFile \"/keys/new key\"\n\tSendEnv \"A\" \"B\"\n"
+    );
+    assert_eq!(
+        SshConfig::parse(&content)
+            .find_host("a")
+            .unwrap()
+            .get("IdentityFile"),
+        Some("/keys/new key")
+    );
+}
+
+#[test]
+fn test_upsert_updates_in_place() {
+    let mut config =
+        SshConfig::parse("Host github.com\n    HostName github.com\n    User me\n\nHost *\n  ServerAliveInterval 60\n");
+
+    assert!(config.upsert_host("github.com", &[("User", "git"), ("IdentitiesOnly", "yes")]));
+    assert!(!config.upsert_host("github.com", &[("User", "git")]));
+    assert_eq!(
+        config.to_string(),
+        "Host github.com\n    HostName github.com\n    User git\n    IdentitiesOnly yes\n\nHost *\n  ServerAliveInterval 60\n"
+    );
+}
+
+#[test]
+fn test_upsert_appends_and_remove() {
+    let mut config = SshConfig::parse("Host a\n\tUser x\n");
+    config.upsert_host("b", &[("HostName", "b.example"), ("User", "git")]);
+    assert_eq!(
+        config.to_string(),
+        "Host a\n\tUser x\n\nHost b\n\tHostName b.example\n\tUser git\n"
+    );
+
+    assert!(config.remove_host("b"));
+    assert!(!config.remove_host("b"));
+    assert_eq!(config.to_string(), "Host a\n\tUser x\n");
+
+    let mut config = SshConfig::default();
+    config.upsert_host("c", &[("User", "git")]);
+    assert_eq!(config.to_string(), "Host c\n\tUser git\n");
+}
+
+#[test]
+fn test_conflicts() {
+    let config = SshConfig::parse(
+        "Host *.com !gitlab.com\n  User me\n  IdentityFile ~/.ssh/a\n\nHost github.com\n  User git\n  IdentityFile ~/.ssh/b\n\nHost gitlab.com\n  User git\n\nHost github.com\n  Port 22\n",
+    );
+
+    assert_eq!(
+        config.conflicts(),
+        vec![
+            SshConflict::Shadowed {
+                host: "github.com".to_string(),
+                line: 5,
+                key: "User".to_string(),
+                by: "*.com !gitlab.com".to_string(),
+                by_line: 1,
+            },
+            SshConflict::Duplicate {
+                pattern: "github.com".to_string(),
+                first_line: 5,
+                line: 12,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_wildcard_match() {
+    assert!(wildcard_match("*", "github.com"));
+    assert!(wildcard_match("git*.c?m", "GitHub.com"));
+    assert!(!wildcard_match("*.org", "github.com"));
+}
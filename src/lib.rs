@@ -1,11 +1,13 @@
+mod cli;
 mod enums;
 mod utilities;
 
+pub use cli::*;
 pub use enums::*;
 pub use utilities::*;
 
+use std::process::ExitCode;
 
-pub fn main_from_lib() {
-    git_info_test();
-    // test_prompt_with_options();
+pub fn main_from_lib() -> ExitCode {
+    run_cli()
 }
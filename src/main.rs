@@ -1,5 +1,6 @@
 use gitsy::*;
+use std::process::ExitCode;
 
-fn main() {
-    main_from_lib();
+fn main() -> ExitCode {
+    main_from_lib()
 }
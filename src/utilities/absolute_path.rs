@@ -17,6 +17,9 @@ use std::path::{Path, PathBuf};
 /// # Examples
 ///
 /// ```
+/// use gitsy::get_abs_path;
+/// use std::path::Path;
+///
 /// let path = get_abs_path(Some(Path::new("/example")), Path::new("/fallback"), &[""], "example_context");
 /// ```
 pub fn get_abs_path<I, T>(
@@ -29,7 +32,32 @@ where
     I: IntoIterator<Item = T>,
     T: AsRef<Path>,
 {
-    let result = path.map_or_else(
+    let result = resolve_abs_path(path, fallback_parent, fallback_components);
+
+    if result.exists() {
+        Ok(result)
+    } else {
+        Err(anyhow!(
+            "Path not found: {}, Context: {}",
+            result.display(),
+            context
+        ))
+    }
+}
+
+/// Computes the absolute path like [`get_abs_path`], without requiring it to exist.
+///
+/// Used for files that are about to be created, such as a new key pair.
+pub fn resolve_abs_path<I, T>(
+    path: Option<&Path>,
+    fallback_parent: &Path,
+    fallback_components: I,
+) -> PathBuf
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<Path>,
+{
+    path.map_or_else(
         || {
             let buffed_path: PathBuf = fallback_components
                 .into_iter()
@@ -44,17 +72,7 @@ where
                 fallback_parent.join(buffed_path)
             }
         },
-    );
-
-    if result.exists() {
-        Ok(result)
-    } else {
-        Err(anyhow!(
-            "Path not found: {}, Context: {}",
-            result.display(),
-            context
-        ))
-    }
+    )
 }
 
 //...USAGE
@@ -78,3 +96,52 @@ pub fn get_pathbuf<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
         Err(anyhow!("Provided path is not absolute"))
     }
 }
+
+/// Displays `path` with the home directory shortened to `~`.
+pub fn tilde_path<P: AsRef<Path>>(path: P) -> String {
+    let path = path.as_ref();
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(relative) if relative.as_os_str().is_empty() => "~".to_string(),
+        Some(relative) => format!("~/{}", relative.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Expands a leading `~` or `~/` to the home directory, as git does for path values.
+pub fn expand_tilde(path: &str) -> PathBuf {
+    let home = dirs::home_dir();
+    match (path, home) {
+        ("~", Some(home)) => home,
+        (_, Some(home)) if path.starts_with("~/") => home.join(&path[2..]),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// The path of `program` as the shell would run it: as given when it contains a
+/// separator, otherwise the first executable match on `PATH`.
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    let is_executable = |path: &Path| {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            path.metadata().is_ok_and(|metadata| {
+                metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            path.is_file() || path.with_extension("exe").is_file()
+        }
+    };
+
+    if program.contains(std::path::MAIN_SEPARATOR) || program.contains('/') {
+        let path = expand_tilde(program);
+        return is_executable(&path).then_some(path);
+    }
+
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|path| is_executable(path))
+    })
+}
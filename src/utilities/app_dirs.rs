@@ -0,0 +1,29 @@
+use anyhow::{anyhow, Result};
+use std::{env, path::PathBuf};
+
+/// Overrides the directory gitsy keeps its own configuration in.
+pub const CONFIG_DIR_ENV: &str = "GITSY_CONFIG_DIR";
+
+/// Returns gitsy's configuration directory, `$XDG_CONFIG_HOME/gitsy` unless overridden.
+pub fn get_config_dir() -> Result<PathBuf> {
+    match env::var_os(CONFIG_DIR_ENV) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => dirs::config_dir()
+            .map(|dir| dir.join("gitsy"))
+            .ok_or_else(|| anyhow!("Failed to determine the config directory")),
+    }
+}
+
+/// Overrides the directory gitsy keeps backups and other state in.
+pub const STATE_DIR_ENV: &str = "GITSY_STATE_DIR";
+
+/// Returns gitsy's state directory, `$XDG_STATE_HOME/gitsy` unless overridden.
+pub fn get_state_dir() -> Result<PathBuf> {
+    match env::var_os(STATE_DIR_ENV) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("gitsy"))
+            .ok_or_else(|| anyhow!("Failed to determine the state directory")),
+    }
+}
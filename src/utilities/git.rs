@@ -19,6 +19,94 @@ pub fn get_git_top_level_dir() -> Result<PathBuf> {
     }
 }
 
+// Function to get the effective value of a config key, as git itself resolves it
+pub fn get_git_value(key: &str) -> Result<Option<String>> {
+    if crate::config_backend() == crate::GitConfigBackend::Native {
+        let key = crate::ConfigKey::parse(key)?.to_string();
+        return Ok(crate::GitConfig::list_all()?
+            .into_iter()
+            .rfind(|entry| entry.key == key)
+            .map(|entry| entry.value_str().trim().to_string()));
+    }
+
+    let output = Command::new("git")
+        .args(["config", "--get", key])
+        .output()?;
+
+    if output.status.success() {
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    } else {
+        Ok(None)
+    }
+}
+
+// Function to get the local config file of the repository at `repo`
+pub fn get_git_config_file(repo: &Path) -> Result<PathBuf> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["rev-parse", "--git-path", "config"])
+        .output()?;
+
+    if output.status.success() {
+        Ok(repo.join(String::from_utf8_lossy(&output.stdout).trim()))
+    } else {
+        Err(anyhow!("{} is not a git repository", repo.display()))
+    }
+}
+
+// Function to list the remotes of the current repository with their fetch URLs
+pub fn get_remote_urls() -> Result<Vec<(String, String)>> {
+    remote_urls(crate::GitConfig::Local)
+}
+
+// Function to list the remotes of the repository at `repo` with their fetch URLs
+pub fn get_remote_urls_in(repo: &Path) -> Result<Vec<(String, String)>> {
+    remote_urls(crate::GitConfig::File(get_git_config_file(repo)?))
+}
+
+fn remote_urls(scope: crate::GitConfig) -> Result<Vec<(String, String)>> {
+    Ok(scope
+        .get_regexp(r"^remote\..*\.url$")?
+        .into_iter()
+        .filter_map(|(key, url)| {
+            let remote = key.strip_prefix("remote.")?.strip_suffix(".url")?;
+            Some((remote.to_string(), url))
+        })
+        .collect())
+}
+
+// Function to point a remote of the current repository at a new URL
+pub fn set_remote_url(remote: &str, url: &str) -> Result<()> {
+    crate::GitConfig::Local.backup(format!("set remote {}", remote))?;
+    run_set_url(Command::new("git"), remote, url)
+}
+
+// Function to point a remote of the repository at `repo` at a new URL
+pub fn set_remote_url_in(repo: &Path, remote: &str, url: &str) -> Result<()> {
+    crate::GitConfig::File(get_git_config_file(repo)?).backup(format!("set remote {}", remote))?;
+    let mut command = Command::new("git");
+    command.arg("-C").arg(repo);
+    run_set_url(command, remote, url)
+}
+
+fn run_set_url(mut command: Command, remote: &str, url: &str) -> Result<()> {
+    let output = command.args(["remote", "set-url", remote, url]).output()?;
+
+    if output.status.success() {
+        println!("Updated remote {}: {}", remote, url);
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Failed to update remote {} |> {}",
+            remote,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
 #[test]
 fn main() {
     match get_git_top_level_dir() {
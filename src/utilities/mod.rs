@@ -1,11 +1,19 @@
 pub mod absolute_path;
+pub mod app_dirs;
 pub mod git;
+mod permissions;
 mod print;
 mod prompts;
+mod runtime;
 pub mod ssh_dir;
+mod wildmatch;
 
 pub use absolute_path::*;
+pub use app_dirs::*;
 pub use git::*;
+pub use permissions::*;
 pub use print::*;
 pub use prompts::*;
+pub use runtime::*;
 pub use ssh_dir::*;
+pub use wildmatch::*;
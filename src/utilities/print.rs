@@ -26,3 +26,31 @@ macro_rules! printres_opt {
         printres_opt($result)
     };
 }
+
+/// Prints `rows` as left-aligned columns under `headers`.
+pub fn print_table<S: AsRef<str>>(headers: &[&str], rows: &[Vec<S>]) {
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.as_ref().chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", format_row(headers.to_vec()));
+    for row in rows {
+        println!("{}", format_row(row.iter().map(AsRef::as_ref).collect()));
+    }
+}
@@ -1,6 +1,18 @@
-use crate::{PromptResult, PromptWithOptions};
+use crate::{answer_mode, AnswerMode, PromptResult, PromptWithOptions};
+use anyhow::{anyhow, Result};
+use std::{
+    io::{stdin, stdout, IsTerminal, Write},
+    process::Command,
+};
 
 pub fn permission_granted<S>(message: S) -> bool
+where
+    S: AsRef<str>,
+{
+    permission_granted_as(message, answer_mode())
+}
+
+pub fn permission_granted_as<S>(message: S, mode: AnswerMode) -> bool
 where
     S: AsRef<str>,
 {
@@ -10,7 +22,7 @@ where
         default: ("n", "No"),
     };
 
-    match prompt_options.prompt() {
+    match prompt_options.prompt_as(mode) {
         PromptResult::Success(selection) => !prompt_options.is_default(&selection),
         PromptResult::Error(error) => {
             eprintln!("{}", error);
@@ -19,6 +31,37 @@ where
     }
 }
 
+/// Reads a line from the terminal without echoing it, for passphrases.
+///
+/// Echo is turned off through `stty`; where that is unavailable the input stays visible.
+pub fn prompt_secret<S>(message: S) -> Result<String>
+where
+    S: AsRef<str>,
+{
+    if !stdin().is_terminal() {
+        return Err(anyhow!("Cannot ask for a secret without a terminal"));
+    }
+
+    print!("{}: ", message.as_ref());
+    stdout().flush()?;
+    let hidden = set_echo(false);
+    let mut secret = String::new();
+    let read = stdin().read_line(&mut secret);
+    if hidden {
+        set_echo(true);
+        println!();
+    }
+    read?;
+    Ok(secret.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn set_echo(echo: bool) -> bool {
+    Command::new("stty")
+        .arg(if echo { "echo" } else { "-echo" })
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
 #[macro_export]
 macro_rules! permission_granted {
     ($message:expr) => {
@@ -28,10 +71,15 @@ macro_rules! permission_granted {
 
 #[test]
 fn test_permission_granted_yes() {
-    assert!(permission_granted("Continue?"));
+    assert!(permission_granted_as("Continue?", AnswerMode::Yes));
 }
 
 #[test]
 fn test_permission_granted_no() {
-    assert!(!permission_granted("Continue?"));
+    assert!(!permission_granted_as("Continue?", AnswerMode::No));
+}
+
+#[test]
+fn test_permission_granted_default() {
+    assert!(!permission_granted_as("Continue?", AnswerMode::Default));
 }
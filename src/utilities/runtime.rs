@@ -0,0 +1,80 @@
+use crate::{AnswerMode, GitConfigBackend};
+use std::{
+    env,
+    io::{stdin, IsTerminal},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+};
+
+static ANSWER_MODE: RwLock<Option<AnswerMode>> = RwLock::new(None);
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+static CONFIG_BACKEND: RwLock<Option<GitConfigBackend>> = RwLock::new(None);
+
+/// Environment variable holding the answer mode: `yes`, `no`, `default` or `interactive`.
+pub const ANSWER_MODE_ENV: &str = "GITSY_ASSUME";
+
+/// Environment variable selecting the git config backend: `git` or `native`.
+pub const CONFIG_BACKEND_ENV: &str = "GITSY_CONFIG_BACKEND";
+
+/// Answer every prompt according to `mode` for the rest of the run.
+pub fn set_answer_mode(mode: AnswerMode) {
+    *ANSWER_MODE.write().unwrap() = Some(mode);
+}
+
+/// The answer mode in effect: set by a CLI flag, else `GITSY_ASSUME`,
+/// else `Default` when stdin is not a terminal, else `Interactive`.
+pub fn answer_mode() -> AnswerMode {
+    if let Some(mode) = *ANSWER_MODE.read().unwrap() {
+        return mode;
+    }
+
+    match env::var(ANSWER_MODE_ENV).map(|mode| mode.parse::<AnswerMode>()) {
+        Ok(Ok(mode)) => mode,
+        Ok(Err(error)) => {
+            eprintln!("Ignoring {}: {}", ANSWER_MODE_ENV, error);
+            fallback_answer_mode()
+        }
+        Err(_) => fallback_answer_mode(),
+    }
+}
+
+fn fallback_answer_mode() -> AnswerMode {
+    if stdin().is_terminal() {
+        AnswerMode::Interactive
+    } else {
+        AnswerMode::Default
+    }
+}
+
+/// Report mutations instead of performing them for the rest of the run.
+pub fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Read and write git config through `backend` for the rest of the run.
+pub fn set_config_backend(backend: GitConfigBackend) {
+    *CONFIG_BACKEND.write().unwrap() = Some(backend);
+}
+
+/// The git config backend in effect: set by a CLI flag, else `GITSY_CONFIG_BACKEND`,
+/// else `Git` when a git binary is available and `Native` otherwise.
+pub fn config_backend() -> GitConfigBackend {
+    if let Some(backend) = *CONFIG_BACKEND.read().unwrap() {
+        return backend;
+    }
+
+    match env::var(CONFIG_BACKEND_ENV).map(|backend| backend.parse::<GitConfigBackend>()) {
+        Ok(Ok(backend)) => backend,
+        Ok(Err(error)) => {
+            eprintln!("Ignoring {}: {}", CONFIG_BACKEND_ENV, error);
+            GitConfigBackend::detect()
+        }
+        Err(_) => GitConfigBackend::detect(),
+    }
+}
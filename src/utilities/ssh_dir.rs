@@ -1,5 +1,8 @@
 use anyhow::{Context, Result};
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
 pub fn get_ssh_home() -> Result<PathBuf> {
     let error_msg = "Failed to determine SSH home directory";
@@ -19,3 +22,33 @@ pub fn get_ssh_home() -> Result<PathBuf> {
         |path: PathBuf| Ok(path.join(".ssh")),
     )
 }
+
+/// Whether `path` holds a private key, judged by its PEM or OpenSSH armour.
+pub fn is_private_key<P: AsRef<Path>>(path: P) -> bool {
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    let mut first_line = String::new();
+    std::io::BufRead::read_line(&mut std::io::BufReader::new(file), &mut first_line).is_ok()
+        && first_line.starts_with("-----BEGIN")
+        && first_line.contains("PRIVATE KEY")
+}
+
+/// Every file in `dir` and its subdirectories, such as the `<host>/<name>` keys gitsy creates.
+pub fn list_ssh_files<P: AsRef<Path>>(dir: P) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return files;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            files.extend(list_ssh_files(&path));
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    files
+}
@@ -0,0 +1,92 @@
+/// Matches `text` against a git wildmatch pattern, as used by `includeIf` conditions:
+/// `*` and `?` stop at `/`, `**/` spans any number of directories and `[...]` is a character class.
+pub fn wildmatch(pattern: &str, text: &str, ignore_case: bool) -> bool {
+    let fold = |s: &str| -> Vec<char> {
+        if ignore_case {
+            s.to_lowercase().chars().collect()
+        } else {
+            s.chars().collect()
+        }
+    };
+    match_chars(&fold(pattern), &fold(text))
+}
+
+fn match_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            match_chars(rest, text)
+                || (0..text.len()).any(|i| text[i] == '/' && match_chars(rest, &text[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| match_chars(rest, &text[i..])),
+        ['*', rest @ ..] => {
+            let segment = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=segment).any(|i| match_chars(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => {
+            matches!(text.first(), Some(&c) if c != '/') && match_chars(rest, &text[1..])
+        }
+        ['[', rest @ ..] => match (text.first(), match_class(rest)) {
+            (Some(&c), Some((matches, len))) if c != '/' && matches(c) => {
+                match_chars(&rest[len..], &text[1..])
+            }
+            (_, Some(_)) => false,
+            //> An unterminated class is a literal '['
+            (Some('['), None) => match_chars(rest, &text[1..]),
+            (_, None) => false,
+        },
+        ['\\', c, rest @ ..] => text.first() == Some(c) && match_chars(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && match_chars(rest, &text[1..]),
+    }
+}
+
+/// Parses the class after `[`, returning its predicate and the length up to and including `]`.
+fn match_class(pattern: &[char]) -> Option<(impl Fn(char) -> bool, usize)> {
+    let negated = matches!(pattern.first(), Some('!') | Some('^'));
+    let start = usize::from(negated);
+    //> A ']' right after the opening bracket is part of the class
+    let end = pattern
+        .iter()
+        .enumerate()
+        .skip(start + 1)
+        .find(|(_, &c)| c == ']')
+        .map(|(index, _)| index)?;
+
+    let members: Vec<char> = pattern[start..end].to_vec();
+    let predicate = move |c: char| {
+        let mut found = false;
+        let mut index = 0;
+        while index < members.len() {
+            if index + 2 < members.len() && members[index + 1] == '-' {
+                found |= (members[index]..=members[index + 2]).contains(&c);
+                index += 3;
+            } else {
+                found |= members[index] == c;
+                index += 1;
+            }
+        }
+        found != negated
+    };
+    Some((predicate, end + 1))
+}
+
+#[test]
+fn test_wildmatch() {
+    assert!(wildmatch("**/work/**", "/home/jane/work/app/.git", false));
+    assert!(wildmatch(
+        "/home/*/work/**",
+        "/home/jane/work/app/.git",
+        false
+    ));
+    assert!(!wildmatch(
+        "/home/*/work/**",
+        "/home/jane/x/work/app/.git",
+        false
+    ));
+    assert!(wildmatch("**/app/.git", "app/.git", false));
+    assert!(wildmatch("feature/*", "feature/login", false));
+    assert!(!wildmatch("feature/*", "feature/a/b", false));
+    assert!(wildmatch("release-[0-9]", "release-7", false));
+    assert!(!wildmatch("release-[!0-9]", "release-7", false));
+    assert!(wildmatch("/HOME/**", "/home/jane", true));
+}